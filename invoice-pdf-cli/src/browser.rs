@@ -0,0 +1,30 @@
+use std::sync::{Arc, Mutex};
+
+use invoice_pdf::{ChromeDriver, error::AddContext};
+
+/// A chromedriver process that is only started the first time a PDF is needed
+///
+/// Clones share the same process, so `main` can keep one handle to guarantee shutdown while the
+/// running command holds another. The process is also stopped when the last handle is dropped.
+#[derive(Clone, Default)]
+pub struct LazyChromeDriver(Arc<Mutex<Option<ChromeDriver>>>);
+
+impl LazyChromeDriver {
+    /// Start chromedriver unless it is already running
+    pub fn ensure_started(&self) -> Result<(), invoice_pdf::Error> {
+        let mut driver = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        if driver.is_none() {
+            *driver = Some(ChromeDriver::start().add_context("starting chromedriver in cli")?);
+        }
+        Ok(())
+    }
+
+    /// Stop chromedriver if it was started
+    pub fn shutdown(&self) -> Result<(), invoice_pdf::Error> {
+        let driver = self.0.lock().unwrap_or_else(|e| e.into_inner()).take();
+        match driver {
+            Some(driver) => driver.stop().add_context("stopping chromedriver from cli"),
+            None => Ok(()),
+        }
+    }
+}
@@ -1,11 +1,22 @@
 use std::{
     fs,
-    io::{self, Read},
-    path::PathBuf,
+    io::{self, BufRead, BufReader, Read},
+    net::SocketAddr,
+    path::{Path, PathBuf},
 };
 
-use clap::Parser;
-use invoice_pdf::{Invoice, error::AddContext};
+use chrono::{DateTime, FixedOffset, NaiveDate};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use invoice_pdf::{
+    Invoice, defaults::InvoiceDefaults, error::AddContext, import::ndjson::NdjsonReader,
+    late_fees::LateFeePolicy, validation::ValidationRules,
+};
+
+use crate::{
+    errors::ErrorFormat,
+    output::{Bundle, Output},
+    watch::Watch,
+};
 
 fn read_until_eof() -> io::Result<String> {
     let mut input = String::new();
@@ -13,22 +24,282 @@ fn read_until_eof() -> io::Result<String> {
     Ok(input)
 }
 
-#[derive(Debug, Parser)]
-pub struct Cli {
-    /// Path to the JSON file with invoice data to print
+/// The layout of the invoice data given to the cli
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    /// A JSON array of invoices matching the serde layout of [`Invoice`]
+    Json,
+    /// A CSV file with a header row and one line item per row
+    Csv,
+    /// An ABC Accounting invoice export
+    Abc,
+    /// A YAML sequence of invoices matching the serde layout of [`Invoice`]
+    Yaml,
+    /// A TOML document with an `invoices` array of tables
+    Toml,
+    /// Newline delimited JSON with one invoice per line, read and printed one line at a time
+    Ndjson,
+}
+
+impl InputFormat {
+    /// Guess the format of a data file from its extension
+    fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "json" => Some(Self::Json),
+            "csv" => Some(Self::Csv),
+            "abc" => Some(Self::Abc),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "toml" => Some(Self::Toml),
+            "ndjson" | "jsonl" => Some(Self::Ndjson),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a date given as YYYY-MM-DD, taken as midnight UTC, or as an RFC 3339 datetime
+fn parse_as_of(s: &str) -> Result<DateTime<FixedOffset>, String> {
+    DateTime::parse_from_rfc3339(s).or_else(|_| {
+        NaiveDate::parse_from_str(s, "%Y-%m-%d")
+            .map(|date| {
+                date.and_time(chrono::NaiveTime::MIN)
+                    .and_utc()
+                    .fixed_offset()
+            })
+            .map_err(|_| format!("'{s}' is not a YYYY-MM-DD date or an RFC 3339 datetime"))
+    })
+}
+
+/// Arguments shared by every subcommand that reads invoices
+#[derive(Debug, Args)]
+pub struct Input {
+    /// Path to the file with invoice data. Reads from stdin if omitted
     #[arg(short, long)]
     pub data: Option<PathBuf>,
 
-    /// Path to the directory where PDF outputs should be saved
-    #[arg(short, long)]
-    pub out: Option<PathBuf>,
+    /// The format of the invoice data. If omitted, the format is chosen from the extension of
+    /// `--data`, falling back to JSON
+    #[arg(short, long, value_enum)]
+    pub format: Option<InputFormat>,
+
+    /// Values merged under every invoice read, from the config file
+    #[arg(skip)]
+    pub defaults: InvoiceDefaults,
+
+    /// Severity of each validation rule checked before rendering, from the config file
+    #[arg(skip)]
+    pub rules: ValidationRules,
+
+    /// Turn every invoice still owing money after its due date into an overdue reminder that
+    /// itemizes the config's `[late_fees]` as of this date, given as YYYY-MM-DD or an RFC 3339
+    /// datetime. Each reminder's id gets a `-reminder-YYYY-MM-DD` suffix. Invoices that are not
+    /// overdue are skipped
+    #[arg(long, value_name = "DATE", value_parser = parse_as_of)]
+    pub overdue_as_of: Option<DateTime<FixedOffset>>,
+
+    /// Late fees added to overdue reminders, from the config file
+    #[arg(skip)]
+    pub late_fees: LateFeePolicy,
+}
+
+/// How the `totals` subcommand prints its results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TotalsFormat {
+    /// An aligned plain text table
+    Table,
+    /// A JSON array with one object per invoice
+    Json,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Print invoices to PDF using chromedriver
+    Render {
+        #[command(flatten)]
+        input: Input,
+
+        #[command(flatten)]
+        output: Output,
+
+        #[command(flatten)]
+        bundle: Bundle,
+
+        #[command(flatten)]
+        watch: Watch,
+    },
+    /// Render invoices to HTML without starting a browser
+    Html {
+        #[command(flatten)]
+        input: Input,
+
+        #[command(flatten)]
+        output: Output,
+
+        #[command(flatten)]
+        watch: Watch,
+    },
+    /// Parse, check and render invoices without printing them, reporting any that fail
+    Validate {
+        #[command(flatten)]
+        input: Input,
+    },
+    /// Print the total, amount paid and net amount due of each invoice
+    Totals {
+        #[command(flatten)]
+        input: Input,
+
+        /// How to print the totals
+        #[arg(short, long, value_enum, default_value_t = TotalsFormat::Table)]
+        output: TotalsFormat,
+    },
+    /// Serve HTTP endpoints that render invoices posted as JSON
+    Serve {
+        /// Address and port to listen on
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        bind: SocketAddr,
 
-    /// If set, also saves an html version of the invoice to ./{invoice_id}.html
-    #[arg(short = 'D', long)]
-    pub debug: bool,
+        /// Maximum number of browser sessions printing PDFs at once
+        #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u16).range(1..))]
+        sessions: u16,
+    },
+    /// Print the JSON Schema describing the invoice input format
+    Schema {
+        /// Describe an array of invoices, as read by the cli, instead of a single invoice
+        #[arg(long)]
+        batch: bool,
+    },
 }
 
-impl Cli {
+#[derive(Debug, Parser)]
+pub struct Cli {
+    /// Path to a config file with default invoice values, page settings and output naming.
+    /// Defaults to `invoice-pdf.toml` in the working directory or
+    /// `$XDG_CONFIG_HOME/invoice-pdf/`, if one exists
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Path to a TOML or JSON file of named sender profiles, which invoices pick with
+    /// `"sender_profile": "<name>"`. Adds to the profiles in the config file
+    #[arg(long, global = true)]
+    pub profiles: Option<PathBuf>,
+
+    /// Path to a JSON or CSV customer file. Invoices may then give `bill_to` and `ship_to` as an
+    /// account id, or leave out `bill_to` to bill the customer of their `acct_id`. Replaces the
+    /// `customers` file of the config
+    #[arg(long, global = true)]
+    pub customers: Option<PathBuf>,
+
+    /// Reject invoices with decimals that have more places than their field keeps, or that are
+    /// given as JSON numbers with a fraction, instead of rounding them. Rounding done without this
+    /// flag is listed in the batch report and by `validate`
+    #[arg(long, global = true)]
+    pub strict_decimals: bool,
+
+    /// How errors are printed to stderr. Whatever the format, the exit status tells the error
+    /// category apart: 65 for bad input, 69 when the browser is unavailable, 70 when rendering
+    /// fails, 74 for file errors and 1 for anything else
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Text)]
+    pub error_format: ErrorFormat,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// The id of an invoice in the JSON layout, if it has one
+fn invoice_id(value: &serde_json::Value) -> Option<String> {
+    value.get("id").and_then(|id| id.as_str()).map(String::from)
+}
+
+impl Input {
+    /// The format to parse the invoice data as
+    pub fn input_format(&self) -> InputFormat {
+        self.format
+            .or_else(|| self.data.as_deref().and_then(InputFormat::from_path))
+            .unwrap_or(InputFormat::Json)
+    }
+
+    /// Name of the input in error locations: the data file, or `stdin`
+    fn source_name(&self) -> String {
+        match &self.data {
+            Some(path) => path.to_string_lossy().to_string(),
+            None => String::from("stdin"),
+        }
+    }
+
+    /// Lazily read the invoices to print
+    ///
+    /// Errors that prevent reading the input at all are returned immediately. NDJSON input is
+    /// parsed one line at a time, so an error for a single line is yielded in place of that
+    /// invoice and the following lines can still be read. Every other format is parsed up front.
+    /// Errors record the input file and, where known, the line and invoice they are about.
+    ///
+    /// With `--overdue-as-of`, overdue invoices are yielded as reminders with their late fees
+    /// and the rest are skipped.
+    pub fn invoices(
+        &self,
+    ) -> Result<
+        Box<dyn Iterator<Item = Result<Invoice, invoice_pdf::Error>> + Send>,
+        invoice_pdf::Error,
+    > {
+        let invoices = self.parsed_invoices()?;
+        let Some(as_of) = self.overdue_as_of else {
+            return Ok(invoices);
+        };
+        let late_fees = self.late_fees.clone();
+        Ok(Box::new(invoices.filter_map(
+            move |invoice| match invoice {
+                Ok(invoice) => invoice.overdue_reminder(&late_fees, &as_of).transpose(),
+                Err(e) => Some(Err(e)),
+            },
+        )))
+    }
+
+    /// Lazily read the invoices as given, see [`Self::invoices`]
+    fn parsed_invoices(
+        &self,
+    ) -> Result<
+        Box<dyn Iterator<Item = Result<Invoice, invoice_pdf::Error>> + Send>,
+        invoice_pdf::Error,
+    > {
+        if self.input_format() != InputFormat::Ndjson {
+            let invoices = self
+                .get_invoices()
+                .map_err(|e| e.with_source(self.source_name()))?;
+            return Ok(Box::new(invoices.into_iter().map(Ok)));
+        }
+
+        let reader: Box<dyn BufRead + Send> = match &self.data {
+            Some(path) => Box::new(BufReader::new(
+                fs::File::open(path)
+                    .map_err(|e| invoice_pdf::Error::from(e).with_source(self.source_name()))
+                    .add_context(&format!(
+                        "opening invoice data file '{}'",
+                        path.to_str().unwrap_or("UNKNOWN")
+                    ))?,
+            )),
+            None => Box::new(BufReader::new(io::stdin())),
+        };
+        let defaults = self.defaults.clone();
+        let source = self.source_name();
+        Ok(Box::new(NdjsonReader::values(reader).map(
+            move |(line, value)| {
+                value
+                    .and_then(|value| {
+                        let id = invoice_id(&value);
+                        defaults
+                            .invoice_from_value(value)
+                            .map_err(|e| match id {
+                                Some(id) => e.with_invoice_id(id),
+                                None => e,
+                            })
+                            .add_context(&format!("reading invoice from line {line}"))
+                    })
+                    .map_err(|e| e.with_source(&source).at_line(line))
+                    .add_context("parsing NDJSON")
+            },
+        )))
+    }
+
     pub fn get_invoices(&self) -> Result<Vec<Invoice>, invoice_pdf::Error> {
         let raw = match &self.data {
             Some(path) => fs::read_to_string(path)
@@ -42,8 +313,57 @@ impl Cli {
                 .add_context("reading invoice data from stdin")?,
         };
 
-        Ok(serde_json::from_str(&raw)
-            .map_err(|e| invoice_pdf::Error::from(format!("{:?}", e)))
-            .add_context("parsing invoice JSON")?)
+        let values = match self.input_format() {
+            InputFormat::Json => serde_json::from_str(&raw)
+                .map_err(invoice_pdf::Error::from)
+                .add_context("parsing invoice JSON")?,
+            InputFormat::Csv => {
+                return self
+                    .with_defaults(invoice_pdf::import::csv::read_invoices(raw.as_bytes()))
+                    .add_context("parsing invoice CSV");
+            }
+            InputFormat::Abc => {
+                return self
+                    .with_defaults(invoice_pdf::import::abc::read_invoices(raw.as_bytes()))
+                    .add_context("parsing ABC invoice export");
+            }
+            InputFormat::Yaml => invoice_pdf::import::yaml::read_values(raw.as_bytes())
+                .add_context("parsing invoice YAML")?,
+            InputFormat::Toml => invoice_pdf::import::toml::read_values(raw.as_bytes())
+                .add_context("parsing invoice TOML")?,
+            InputFormat::Ndjson => NdjsonReader::values(raw.as_bytes())
+                .map(|(_, value)| value)
+                .collect::<Result<Vec<_>, _>>()
+                .add_context("parsing NDJSON")?,
+        };
+        values
+            .into_iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let id = invoice_id(&value);
+                let context = format!(
+                    "reading invoice {i} with id '{}'",
+                    id.as_deref().unwrap_or("UNKNOWN")
+                );
+                self.defaults
+                    .invoice_from_value(value)
+                    .map_err(|e| match id {
+                        Some(id) => e.with_invoice_id(id).at_index(i),
+                        None => e.at_index(i),
+                    })
+                    .add_context(&context)
+            })
+            .collect()
+    }
+
+    /// Merge the config defaults under invoices from a reader that builds them directly
+    fn with_defaults(
+        &self,
+        invoices: Result<Vec<Invoice>, invoice_pdf::Error>,
+    ) -> Result<Vec<Invoice>, invoice_pdf::Error> {
+        invoices?
+            .into_iter()
+            .map(|invoice| self.defaults.apply_to_invoice(invoice))
+            .collect()
     }
 }
@@ -0,0 +1,315 @@
+use std::{io::Write, time::Instant};
+
+use invoice_pdf::{
+    Invoice, bundle::BundleEntry, decimal::TotalRounding, error::AddContext,
+    generate_pdf_unchecked, page::PageSettings, validation::ValidationRules,
+};
+
+use crate::{
+    browser::LazyChromeDriver,
+    cli::{Input, TotalsFormat},
+    errors,
+    output::{Bundle, NameTemplate, Output, Written, bundle_entry_name},
+    report::Report,
+};
+
+pub fn write_stdout(data: &[u8]) -> Result<(), invoice_pdf::Error> {
+    let mut stdout = std::io::stdout();
+    stdout
+        .write_all(data)
+        .map_err(invoice_pdf::Error::from)
+        .add_context("writing to stdout")?;
+    stdout
+        .flush()
+        .map_err(invoice_pdf::Error::from)
+        .add_context("flushing stdout")
+}
+
+/// Counts the invoices a command could not handle, printing each error as it happens so the
+/// rest of the batch can continue
+#[derive(Default)]
+struct Failures {
+    count: usize,
+}
+
+impl Failures {
+    /// Record the outcome of handling a single invoice
+    fn record(&mut self, result: Result<(), invoice_pdf::Error>) {
+        if let Err(e) = result {
+            errors::print(&e);
+            self.count += 1;
+        }
+    }
+
+    /// Turn the failures into the error the command exits with, if there were any
+    fn check(self, action: &str) -> Result<(), invoice_pdf::Error> {
+        if self.count == 0 {
+            return Ok(());
+        }
+        Err(invoice_pdf::Error::from(format!(
+            "{} invoice(s) could not be {action}",
+            self.count
+        )))
+    }
+}
+
+fn read_invoices(
+    input: &Input,
+) -> Result<impl Iterator<Item = Result<Invoice, invoice_pdf::Error>>, invoice_pdf::Error> {
+    input
+        .invoices()
+        .add_context("deserializing invoices from cli")
+}
+
+/// Check `invoice` against the validation rules and that its logo and payment QR code can be
+/// drawn, printing its warnings
+fn check(invoice: &Invoice, rules: &ValidationRules) -> Result<(), invoice_pdf::Error> {
+    let warnings = invoice
+        .validate_with(rules)
+        .into_result()
+        .add_context("validating invoice")?;
+    invoice.check_assets()?;
+    for issue in &warnings {
+        errors::warn(&invoice.id(), issue);
+    }
+    Ok(())
+}
+
+async fn write_invoice_pdf(
+    invoice: &Invoice,
+    output: &Output,
+    template: &NameTemplate,
+    page: &PageSettings,
+) -> Result<(Written, usize), invoice_pdf::Error> {
+    let data = generate_pdf_unchecked(invoice, page)
+        .await
+        .add_context("generating pdf data from invoice")?;
+    let written = output.write(template, invoice, &data)?;
+    Ok((written, data.len()))
+}
+
+fn with_invoice_id<T>(
+    invoice: &Invoice,
+    result: Result<T, invoice_pdf::Error>,
+) -> Result<T, invoice_pdf::Error> {
+    result
+        .map_err(|e| e.with_invoice_id(invoice.id()))
+        .add_context(&format!("invoice id: {}", invoice.id()))
+}
+
+/// Print every invoice to PDF, either into the files named by `output`, into a single file when
+/// `bundle` is set, or to stdout
+///
+/// Invoices are checked against the validation rules first. Chromedriver is only started once
+/// the first invoice has been read and checked successfully, and is left running for the caller
+/// to shut down. A bundle holds every invoice that printed successfully, and those invoices are
+/// only reported as bundled once the bundle has been written.
+pub async fn render(
+    input: &Input,
+    output: &Output,
+    bundle: &Bundle,
+    page: &PageSettings,
+    driver: &LazyChromeDriver,
+) -> Result<(), invoice_pdf::Error> {
+    let template = output.template("pdf")?;
+    let mut report = Report::new(output.report.clone());
+    let mut entries = Vec::new();
+    let mut bundled = Vec::new();
+    let mut started = Instant::now();
+    for invoice in read_invoices(input)? {
+        let invoice = match invoice {
+            Ok(invoice) => invoice,
+            Err(e) => {
+                report.record(None, started, Err(e));
+                started = Instant::now();
+                continue;
+            }
+        };
+        if let Err(e) = check(&invoice, &input.rules) {
+            report.record(Some(&invoice), started, with_invoice_id(&invoice, Err(e)));
+            started = Instant::now();
+            continue;
+        }
+        driver.ensure_started()?;
+        match bundle.bundle {
+            Some(format) => match bundle_entry(&invoice, format, &template, page).await {
+                Ok(entry) => {
+                    bundled.push((invoice, started.elapsed(), entry.pdf.len()));
+                    entries.push(entry);
+                }
+                Err(e) => report.record(Some(&invoice), started, with_invoice_id(&invoice, Err(e))),
+            },
+            None => {
+                let result = write_invoice_pdf(&invoice, output, &template, page).await;
+                report.record(Some(&invoice), started, with_invoice_id(&invoice, result));
+            }
+        }
+        started = Instant::now();
+    }
+    if let Some(format) = bundle.bundle
+        && !entries.is_empty()
+    {
+        let started = Instant::now();
+        match bundle.write(format, &entries) {
+            Ok(()) => {
+                for (invoice, elapsed, bytes) in &bundled {
+                    let written = Written::Bundle(bundle.bundle_file.clone());
+                    report.record_elapsed(Some(invoice), *elapsed, Ok((written, *bytes)));
+                }
+            }
+            Err(e) => {
+                errors::record_failure(&e);
+                for (invoice, elapsed, _) in &bundled {
+                    let e = invoice_pdf::Error::from(format!("bundle was not written: {e:?}"));
+                    let result = with_invoice_id(invoice, Err(e));
+                    report.record_elapsed(Some(invoice), *elapsed + started.elapsed(), result);
+                }
+            }
+        }
+    }
+    report.finish("printed")
+}
+
+async fn bundle_entry(
+    invoice: &Invoice,
+    format: crate::output::BundleFormat,
+    template: &NameTemplate,
+    page: &PageSettings,
+) -> Result<BundleEntry, invoice_pdf::Error> {
+    let pdf = generate_pdf_unchecked(invoice, page)
+        .await
+        .add_context("generating pdf data from invoice")?;
+    Ok(BundleEntry {
+        name: bundle_entry_name(format, template, invoice)?,
+        pdf,
+    })
+}
+
+/// Check and render every invoice to HTML, either into the files named by `output` or to stdout
+pub async fn html(input: &Input, output: &Output) -> Result<(), invoice_pdf::Error> {
+    let template = output.template("html")?;
+    let mut report = Report::new(output.report.clone());
+    let mut started = Instant::now();
+    for invoice in read_invoices(input)? {
+        match invoice {
+            Ok(invoice) => {
+                for adjustment in invoice.rounding_adjustments() {
+                    errors::adjusted(&invoice.id(), adjustment);
+                }
+                let result = check(&invoice, &input.rules).and_then(|_| {
+                    let html = invoice.render_html()?;
+                    let written = output.write(&template, &invoice, html.as_bytes())?;
+                    Ok((written, html.len()))
+                });
+                report.record(Some(&invoice), started, with_invoice_id(&invoice, result));
+            }
+            Err(e) => report.record(None, started, Err(e)),
+        }
+        started = Instant::now();
+    }
+    report.finish("rendered")
+}
+
+/// Parse, check and render every invoice, printing the id of each one that passes and the
+/// warnings and rounding adjustments of each one checked
+pub async fn validate(input: &Input) -> Result<(), invoice_pdf::Error> {
+    let mut failures = Failures::default();
+    for invoice in read_invoices(input)? {
+        failures.record(invoice.and_then(|invoice| {
+            for adjustment in invoice.rounding_adjustments() {
+                errors::adjusted(&invoice.id(), adjustment);
+            }
+            let result = check(&invoice, &input.rules)
+                .and_then(|_| invoice.render_html())
+                .map(|_| println!("ok: {}", invoice.id()));
+            with_invoice_id(&invoice, result)
+        }));
+    }
+    failures.check("validated")
+}
+
+/// Print the total, amount paid and net amount due of every invoice. The JSON output also has
+/// how much rounding changed each total and whether it was rounded per line or per invoice
+pub async fn totals(input: &Input, output: TotalsFormat) -> Result<(), invoice_pdf::Error> {
+    let mut failures = Failures::default();
+    let mut rows = Vec::new();
+    for invoice in read_invoices(input)? {
+        failures.record(invoice.map(|invoice| {
+            let amount = invoice.rounding().total;
+            rows.push([
+                invoice.id(),
+                amount.format(&invoice.total()),
+                amount.format(&invoice.paid()),
+                amount.format(&invoice.net_due()),
+                invoice.rounding_difference().to_string(),
+                match invoice.total_rounding() {
+                    TotalRounding::PerLine => String::from("per_line"),
+                    TotalRounding::PerInvoice => String::from("per_invoice"),
+                },
+            ])
+        }));
+    }
+
+    match output {
+        TotalsFormat::Json => {
+            let json: Vec<_> = rows
+                .iter()
+                .map(|[id, total, paid, net_due, difference, rounding]| {
+                    serde_json::json!({
+                        "id": id,
+                        "total": total,
+                        "paid": paid,
+                        "net_due": net_due,
+                        "rounding_difference": difference,
+                        "total_rounding": rounding,
+                    })
+                })
+                .collect();
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&json)
+                    .map_err(invoice_pdf::Error::from)
+                    .add_context("printing totals")?
+            );
+        }
+        TotalsFormat::Table => {
+            let header = ["ID", "TOTAL", "PAID", "NET DUE"].map(String::from);
+            let mut widths = header.clone().map(|h| h.len());
+            for row in &rows {
+                for (width, cell) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(cell.len());
+                }
+            }
+            for row in std::iter::once(&header[..]).chain(rows.iter().map(|row| &row[..4])) {
+                println!(
+                    "{:<w0$}  {:>w1$}  {:>w2$}  {:>w3$}",
+                    row[0],
+                    row[1],
+                    row[2],
+                    row[3],
+                    w0 = widths[0],
+                    w1 = widths[1],
+                    w2 = widths[2],
+                    w3 = widths[3]
+                );
+            }
+        }
+    }
+    failures.check("totaled")
+}
+
+/// Print the JSON Schema for one invoice, or for an array of them if `batch` is set
+pub fn schema(batch: bool) -> Result<(), invoice_pdf::Error> {
+    let schema = if batch {
+        invoice_pdf::schema::invoice_batch_schema()
+    } else {
+        invoice_pdf::schema::invoice_schema()
+    };
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&schema)
+            .map_err(invoice_pdf::Error::from)
+            .add_context("printing invoice schema")?
+    );
+    Ok(())
+}
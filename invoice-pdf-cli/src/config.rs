@@ -0,0 +1,303 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use invoice_pdf::{
+    customers::CustomerDirectory,
+    decimal::DecimalMode,
+    defaults::{InvoiceDefaults, SenderProfile},
+    error::AddContext,
+    late_fees::LateFeePolicy,
+    page::PageSettings,
+    validation::ValidationRules,
+};
+use serde::Deserialize;
+
+use crate::{
+    cli::{Command, Input},
+    output::{Output, OverwritePolicy},
+};
+
+/// Name of the configuration file looked for in the working and config directories
+pub const CONFIG_FILE_NAME: &str = "invoice-pdf.toml";
+
+/// Defaults for where outputs are written, used when the matching flag is not given
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    pub out: Option<PathBuf>,
+    pub name: Option<String>,
+    pub overwrite: Option<OverwritePolicy>,
+}
+
+/// Settings read from `invoice-pdf.toml`
+///
+/// ```toml
+/// customers = "./customers.csv"
+/// decimals = "strict"
+///
+/// [invoice]
+/// logo = "./logo.png"
+/// payment_url_base = "https://pay.example.com/invoices/"
+/// post_scripts = ["Thank you for your business"]
+/// terms = "2/10 Net 30"
+///
+/// [invoice.sender]
+/// name = "Hometown Hardware"
+/// address = { line1 = "1 Main St", city = "Town", province_code = "PA", postal_code = "12345" }
+///
+/// [invoice.rounding]
+/// price = { places = 4, mode = "half_up" }
+///
+/// [invoice.profiles.east]
+/// sender = { name = "Hometown Hardware East" }
+/// logo = "./east.png"
+/// post_scripts = ["Remit to PO Box 1, East Town"]
+///
+/// [validation]
+/// negative_price = "off"
+/// overpaid = "warning"
+///
+/// [late_fees]
+/// grace_days = 5
+/// fees = [{ type = "flat", amount = "25" }, { type = "interest", percent = "1.5", per = "month" }]
+///
+/// [page]
+/// size = "a4"
+/// margins = { top = 1.0, bottom = 1.0 }
+///
+/// [output]
+/// out = "./invoices"
+/// name = "{acct_id}/{id}.pdf"
+/// overwrite = "suffix"
+/// ```
+///
+/// Values in `[invoice]` are merged under every invoice read, so the invoice's own values win.
+/// An invoice with `"sender_profile": "east"` takes its sender, logo, payment link and post
+/// scripts from the `east` profile instead of `[invoice]`. `customers` names a JSON or CSV
+/// customer file that `bill_to`, `ship_to` and `acct_id` are looked up in.
+/// `decimals = "strict"` rejects invoices with decimals that would otherwise be rounded.
+/// `[validation]` sets the severity of validation rules: `error`, `warning` or `off`.
+/// `[late_fees]` are the charges `--overdue-as-of` adds to overdue reminders.
+/// Values in `[output]` are used only when the matching flag is not given. Relative paths are
+/// resolved from the working directory.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub customers: Option<PathBuf>,
+    pub decimals: DecimalMode,
+    pub invoice: InvoiceDefaults,
+    pub validation: ValidationRules,
+    pub late_fees: LateFeePolicy,
+    pub page: PageSettings,
+    pub output: OutputConfig,
+}
+
+/// Where to look for a configuration file when `--config` is not given, in order
+fn search_paths() -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::from(CONFIG_FILE_NAME)];
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    if let Some(dir) = config_dir {
+        paths.push(dir.join("invoice-pdf").join(CONFIG_FILE_NAME));
+    }
+    paths
+}
+
+impl Config {
+    /// Parse a configuration file
+    pub fn read(path: &Path) -> Result<Self, invoice_pdf::Error> {
+        let context = format!("reading config file '{}'", path.to_string_lossy());
+        let raw = fs::read_to_string(path)
+            .map_err(invoice_pdf::Error::from)
+            .add_context(&context)?;
+        toml::from_str(&raw)
+            .map_err(invoice_pdf::Error::from)
+            .add_context(&context)
+    }
+
+    /// Load `path` if given, otherwise the first `invoice-pdf.toml` found in the working
+    /// directory or `$XDG_CONFIG_HOME/invoice-pdf/`. Without any file, every setting is empty
+    pub fn load(path: Option<&Path>) -> Result<Self, invoice_pdf::Error> {
+        if let Some(path) = path {
+            return Self::read(path);
+        }
+        match search_paths().into_iter().find(|path| path.is_file()) {
+            Some(path) => Self::read(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Add the sender profiles of a profiles file, replacing config profiles of the same name
+    ///
+    /// The file maps profile names to profiles, as TOML or as JSON when its extension is `.json`:
+    ///
+    /// ```toml
+    /// [west]
+    /// sender = { name = "Hometown Hardware West" }
+    /// payment_url_base = "https://pay.example.com/west/"
+    /// ```
+    pub fn add_profiles(&mut self, path: &Path) -> Result<(), invoice_pdf::Error> {
+        let context = format!("reading sender profiles '{}'", path.to_string_lossy());
+        let raw = fs::read_to_string(path)
+            .map_err(invoice_pdf::Error::from)
+            .add_context(&context)?;
+        let profiles: BTreeMap<String, SenderProfile> =
+            if path.extension().is_some_and(|ext| ext == "json") {
+                serde_json::from_str(&raw).map_err(invoice_pdf::Error::from)
+            } else {
+                toml::from_str(&raw).map_err(invoice_pdf::Error::from)
+            }
+            .add_context(&context)?;
+        self.invoice.profiles.extend(profiles);
+        Ok(())
+    }
+
+    /// Load the customer directory from `path`, or from the config's `customers` file when no
+    /// path is given
+    pub fn load_customers(&mut self, path: Option<&Path>) -> Result<(), invoice_pdf::Error> {
+        let Some(path) = path.or(self.customers.as_deref()) else {
+            return Ok(());
+        };
+        self.invoice.customers = CustomerDirectory::read_file(path)?;
+        Ok(())
+    }
+
+    /// The invoice defaults, reading decimals in the configured mode
+    pub fn invoice_defaults(&self) -> InvoiceDefaults {
+        InvoiceDefaults {
+            decimals: self.decimals,
+            ..self.invoice.clone()
+        }
+    }
+
+    fn apply_input(&self, input: &mut Input) {
+        input.defaults = self.invoice_defaults();
+        input.rules = self.validation.clone();
+        input.late_fees = self.late_fees.clone();
+    }
+
+    fn apply_output(&self, output: &mut Output) {
+        if output.out.is_none() {
+            output.out = self.output.out.clone();
+        }
+        if output.name.is_none() {
+            output.name = self.output.name.clone();
+        }
+        if output.overwrite.is_none() {
+            output.overwrite = self.output.overwrite;
+        }
+    }
+
+    /// Fill the settings of `command` that were not given on the command line
+    pub fn apply(&self, command: &mut Command) {
+        match command {
+            Command::Render { input, output, .. } | Command::Html { input, output, .. } => {
+                self.apply_input(input);
+                self.apply_output(output);
+            }
+            Command::Validate { input } | Command::Totals { input, .. } => {
+                self.apply_input(input);
+            }
+            Command::Serve { .. } | Command::Schema { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use invoice_pdf::{
+        late_fees::LateFee,
+        validation::{Rule, Severity},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config: Config = toml::from_str(
+            r#"
+decimals = "strict"
+
+[invoice]
+logo = "./logo.png"
+post_scripts = ["Thanks"]
+sender = { name = "Sender" }
+terms = "Net 30"
+
+[invoice.rounding]
+quantity = { places = 3, mode = "half_up" }
+
+[invoice.profiles.east]
+sender = { name = "East" }
+
+[validation]
+overpaid = "warning"
+
+[late_fees]
+grace_days = 5
+fees = [{ type = "percent", percent = 2 }]
+
+[page]
+size = "a4"
+margins = { top = 1.0 }
+
+[output]
+name = "{id}.pdf"
+overwrite = "skip"
+"#,
+        )
+        .unwrap();
+        assert_eq!(config.invoice_defaults().decimals, DecimalMode::Strict);
+        assert_eq!(config.invoice.sender.unwrap().name(), "Sender");
+        assert_eq!(config.invoice.profiles["east"].sender.name(), "East");
+        assert_eq!(config.invoice.terms.unwrap().to_string(), "Net 30");
+        assert_eq!(config.late_fees.grace_days, 5);
+        assert_eq!(
+            config.late_fees.fees,
+            [LateFee::Percent { percent: 2.into() }]
+        );
+        assert_eq!(config.invoice.rounding.unwrap().quantity.places, 3);
+        assert_eq!(config.invoice.rounding.unwrap().price.places, 3);
+        assert_eq!(
+            config.validation.severity(Rule::Overpaid),
+            Severity::Warning
+        );
+        assert_eq!(
+            config.validation.severity(Rule::NoLineItems),
+            Severity::Error
+        );
+        assert_eq!(config.page.size, invoice_pdf::page::PageSize::A4);
+        assert_eq!(config.page.margins.top, 1.0);
+        assert_eq!(config.page.margins.left, 1.5);
+        assert_eq!(config.output.overwrite, Some(OverwritePolicy::Skip));
+
+        assert!(toml::from_str::<Config>("[invoice]\nunknown = 1\n").is_err());
+    }
+
+    #[test]
+    fn test_flags_win_over_config() {
+        let config = Config {
+            output: OutputConfig {
+                out: Some(PathBuf::from("config-out")),
+                name: Some(String::from("{acct_id}/{id}.pdf")),
+                overwrite: Some(OverwritePolicy::Suffix),
+            },
+            ..Config::default()
+        };
+        let mut output = Output {
+            out: Some(PathBuf::from("flag-out")),
+            name: None,
+            overwrite: None,
+            report: None,
+        };
+        config.apply_output(&mut output);
+        assert_eq!(output.out, Some(PathBuf::from("flag-out")));
+        assert_eq!(output.name.as_deref(), Some("{acct_id}/{id}.pdf"));
+        assert_eq!(output.overwrite, Some(OverwritePolicy::Suffix));
+    }
+}
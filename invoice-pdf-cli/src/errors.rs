@@ -0,0 +1,206 @@
+use std::sync::OnceLock;
+
+use clap::ValueEnum;
+use invoice_pdf::{
+    decimal::Adjustment,
+    error::{ErrorCategory, ErrorKind},
+    validation::Issue,
+};
+use serde_json::{Value, json};
+
+/// Exit status for malformed or unusable invoice data, `EX_DATAERR` from sysexits.h
+pub const INPUT_EXIT_CODE: i32 = 65;
+/// Exit status when chromedriver or the browser is unavailable, `EX_UNAVAILABLE`
+pub const BROWSER_EXIT_CODE: i32 = 69;
+/// Exit status when an invoice could not be rendered, `EX_SOFTWARE`
+pub const RENDERING_EXIT_CODE: i32 = 70;
+/// Exit status when reading or writing files failed, `EX_IOERR`
+pub const IO_EXIT_CODE: i32 = 74;
+/// Exit status for every other error
+pub const OTHER_EXIT_CODE: i32 = 1;
+
+/// How errors are written to stderr
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    /// The context and cause on one line, separated by `->`
+    #[default]
+    Text,
+    /// One JSON object per line with the kind, category, message, context stack, invoice id and
+    /// input location. The message is the root cause with the errors it wraps
+    Json,
+}
+
+/// The format chosen on the command line, set once at startup
+static FORMAT: OnceLock<ErrorFormat> = OnceLock::new();
+
+/// Category of the first per-invoice error printed, which decides the exit status when a batch
+/// ends with some invoices failed
+static FIRST_FAILURE: OnceLock<ErrorCategory> = OnceLock::new();
+
+/// Choose how errors are printed for the rest of the process
+pub fn set_format(format: ErrorFormat) {
+    let _ = FORMAT.set(format);
+}
+
+fn format() -> ErrorFormat {
+    FORMAT.get().copied().unwrap_or_default()
+}
+
+/// An error as the JSON object printed by `--error-format json`
+pub fn to_json(e: &invoice_pdf::Error) -> Value {
+    let location = e.location();
+    let mut json = json!({
+        "kind": e.kind.name(),
+        "category": e.kind.category(),
+        "message": e.root_cause(),
+        "context": e.context().iter().rev().collect::<Vec<_>>(),
+        "invoice_id": e.invoice_id(),
+        "location": if location.is_empty() { Value::Null } else { json!(location) },
+    });
+    if let ErrorKind::Validation(issues) = &e.kind {
+        json["issues"] = json!(issues);
+    }
+    json
+}
+
+/// Print a validation warning about an invoice that is still handled
+pub fn warn(invoice_id: &str, issue: &Issue) {
+    match format() {
+        ErrorFormat::Text => eprintln!("Warning: invoice {invoice_id}: {issue}"),
+        ErrorFormat::Json => eprintln!(
+            "{}",
+            json!({
+                "kind": "validation_warning",
+                "invoice_id": invoice_id,
+                "issue": issue,
+            })
+        ),
+    }
+}
+
+/// Print a decimal of an invoice that was rounded while reading it
+pub fn adjusted(invoice_id: &str, adjustment: &Adjustment) {
+    match format() {
+        ErrorFormat::Text => eprintln!("Rounded: invoice {invoice_id}: {adjustment}"),
+        ErrorFormat::Json => eprintln!(
+            "{}",
+            json!({
+                "kind": "rounding_adjustment",
+                "invoice_id": invoice_id,
+                "adjustment": adjustment,
+            })
+        ),
+    }
+}
+
+/// Note that an invoice of the batch failed without printing the error
+pub fn record_failure(e: &invoice_pdf::Error) {
+    let _ = FIRST_FAILURE.set(e.kind.category());
+}
+
+/// Print an error about a single invoice while the rest of the batch carries on
+pub fn print(e: &invoice_pdf::Error) {
+    record_failure(e);
+    match format() {
+        ErrorFormat::Text => eprintln!("{e:?}"),
+        ErrorFormat::Json => eprintln!("{}", to_json(e)),
+    }
+}
+
+/// The exit status for the error a command ended with
+///
+/// A command that fails because some invoices of a batch failed exits with the status of the
+/// first of those failures.
+pub fn exit_code(e: &invoice_pdf::Error) -> i32 {
+    let category = match (&e.kind, FIRST_FAILURE.get()) {
+        (ErrorKind::Other(_), Some(first)) => *first,
+        (kind, _) => kind.category(),
+    };
+    match category {
+        ErrorCategory::Input => INPUT_EXIT_CODE,
+        ErrorCategory::Rendering => RENDERING_EXIT_CODE,
+        ErrorCategory::Browser => BROWSER_EXIT_CODE,
+        ErrorCategory::Io => IO_EXIT_CODE,
+        ErrorCategory::Other => OTHER_EXIT_CODE,
+    }
+}
+
+/// Print the error a command ended with and exit with the status of its category
+pub fn exit(e: &invoice_pdf::Error) -> ! {
+    match format() {
+        ErrorFormat::Text => eprintln!("Error: {e:?}"),
+        ErrorFormat::Json => eprintln!("{}", to_json(e)),
+    }
+    std::process::exit(exit_code(e))
+}
+
+#[cfg(test)]
+mod tests {
+    use invoice_pdf::error::AddContext;
+
+    use super::*;
+
+    #[test]
+    fn test_to_json() {
+        let e: Result<(), _> = Err(invoice_pdf::Error::invalid("unknown customer 'C-9'")
+            .with_source("invoices.ndjson")
+            .at_line(4)
+            .with_invoice_id("7"));
+        let e = e
+            .add_context("reading invoice from line 4")
+            .add_context("parsing NDJSON")
+            .unwrap_err();
+        assert_eq!(
+            to_json(&e),
+            json!({
+                "kind": "invalid",
+                "category": "input",
+                "message": "unknown customer 'C-9'",
+                "context": ["parsing NDJSON", "reading invoice from line 4"],
+                "invoice_id": "7",
+                "location": {"source": "invoices.ndjson", "line": 4, "column": null, "index": null},
+            })
+        );
+        assert_eq!(exit_code(&e), INPUT_EXIT_CODE);
+
+        let e = invoice_pdf::Error::from(std::io::Error::other("disk full"));
+        assert_eq!(to_json(&e)["message"], "I/O error: disk full");
+        assert_eq!(
+            exit_code(&invoice_pdf::Error::new(ErrorKind::PortInUse(4444))),
+            BROWSER_EXIT_CODE
+        );
+    }
+
+    #[test]
+    fn test_validation_issues_in_json() {
+        let invoice = invoice_pdf::InvoiceBuilder::default()
+            .id("7")
+            .sender(
+                invoice_pdf::PartyBuilder::default()
+                    .name("S")
+                    .build()
+                    .unwrap(),
+            )
+            .bill_to(
+                invoice_pdf::PartyBuilder::default()
+                    .name("B")
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+        let e = invoice.validate().into_result().unwrap_err();
+        let json = to_json(&e);
+        assert_eq!(json["kind"], "validation");
+        assert_eq!(
+            json["issues"],
+            json!([{
+                "rule": "no_line_items",
+                "severity": "error",
+                "path": "line_items",
+                "message": "invoice has no line items",
+            }])
+        );
+        assert_eq!(exit_code(&e), INPUT_EXIT_CODE);
+    }
+}
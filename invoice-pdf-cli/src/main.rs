@@ -1,79 +1,98 @@
-use std::{io::Write, process::Child};
-
 use clap::Parser;
-use invoice_pdf::{Invoice, error::AddContext, generate_pdf, start_chromedriver};
+use invoice_pdf::{decimal::DecimalMode, error::AddContext};
 
-use crate::cli::Cli;
+use crate::{
+    browser::LazyChromeDriver,
+    cli::{Cli, Command},
+    config::Config,
+};
 
+mod browser;
 mod cli;
+mod commands;
+mod config;
+mod errors;
+mod output;
+mod report;
+mod serve;
+mod watch;
 
-fn kill_chrome(chrome_process: &mut Child) -> Result<(), invoice_pdf::Error> {
-    chrome_process
-        .kill()
-        .map_err(invoice_pdf::Error::from)
-        .add_context("killing chromedriver process from cli")?;
-    Ok(())
-}
+/// Exit status used when the cli is stopped with Ctrl-C, matching the shell convention for SIGINT
+const INTERRUPTED_EXIT_CODE: i32 = 130;
 
-async fn write_invoice_pdf(invoice: &Invoice, cli: &Cli) -> Result<(), invoice_pdf::Error> {
-    let data = generate_pdf(invoice)
-        .await
-        .add_context("generating pdf data from invoice")
-        .add_context(&format!("invoice id: {}", invoice.id()))?;
-    match &cli.out {
-        Some(out) => {
-            let path = out.join(format!("{}.pdf", invoice.id()));
-            if std::fs::write(&path, data).is_err() {
-                eprintln!(
-                    "Failed to write invoice '{}' to '{}'",
-                    invoice.id(),
-                    &path.to_string_lossy()
-                );
-                write_invoice_pdf_to_stdout(invoice).await
+async fn run(mut cli: Cli, driver: LazyChromeDriver) -> Result<(), invoice_pdf::Error> {
+    let mut config = Config::load(cli.config.as_deref()).add_context("loading cli config")?;
+    if let Some(path) = &cli.profiles {
+        config.add_profiles(path)?;
+    }
+    config.load_customers(cli.customers.as_deref())?;
+    if cli.strict_decimals {
+        config.decimals = DecimalMode::Strict;
+    }
+    config.apply(&mut cli.command);
+    match &cli.command {
+        Command::Render {
+            input,
+            output,
+            bundle,
+            watch,
+        } => {
+            let render = || commands::render(input, output, bundle, &config.page, &driver);
+            if watch.watch {
+                watch::watch(input, watch, render).await
+            } else {
+                render().await
+            }
+        }
+        Command::Html {
+            input,
+            output,
+            watch,
+        } => {
+            let html = || commands::html(input, output);
+            if watch.watch {
+                watch::watch(input, watch, html).await
             } else {
-                Ok(())
+                html().await
             }
         }
-        None => write_invoice_pdf_to_stdout(invoice).await,
+        Command::Validate { input } => commands::validate(input).await,
+        Command::Totals { input, output } => commands::totals(input, *output).await,
+        Command::Serve { bind, sessions } => {
+            serve::serve(*bind, usize::from(*sessions), &config, &driver).await
+        }
+        Command::Schema { batch } => commands::schema(*batch),
     }
 }
 
-async fn write_invoice_pdf_to_stdout(invoice: &Invoice) -> Result<(), invoice_pdf::Error> {
-    let mut buf = generate_pdf(invoice)
-        .await
-        .add_context("generating invoice pdf")
-        .add_context("printing to stdout")?;
-    std::io::stdout()
-        .write_all(&mut buf)
-        .map_err(invoice_pdf::Error::from)
-        .add_context("writing invoice pdf to stdout")?;
-    std::io::stdout()
-        .flush()
-        .map_err(invoice_pdf::Error::from)
-        .add_context("flushing stdout")
-        .add_context("printing to stdout")?;
-    Ok(())
-}
-
 #[tokio::main]
-async fn main() -> Result<(), invoice_pdf::Error> {
-    let mut chrome_process = start_chromedriver().add_context("starting chromedriver in cli")?;
+async fn main() {
     let cli = Cli::parse();
-    let invoices = cli
-        .get_invoices()
-        .or_else(|e| {
-            kill_chrome(&mut chrome_process)?;
-            Err(e)
-        })
-        .add_context("deserializing invoices from cli")?;
-    for invoice in invoices {
-        if cli.debug {
-            std::fs::write(format!("{}.html", invoice.id()), invoice.render_html()?)
-                .map_err(invoice_pdf::Error::from)
-                .add_context(&format!("writing {}.html to disk", invoice.id()))?;
+    errors::set_format(cli.error_format);
+    let driver = LazyChromeDriver::default();
+
+    // The command runs in its own task so Ctrl-C can be handled even while it is blocked reading
+    // input. Whichever way the command ends, chromedriver is shut down through `driver` before
+    // exiting.
+    let command = tokio::spawn(run(cli, driver.clone()));
+    tokio::select! {
+        result = command => {
+            let shutdown = driver.shutdown();
+            let result = result
+                .map_err(|e| invoice_pdf::Error::from(e.to_string()))
+                .add_context("running cli command")
+                .and_then(|result| result)
+                .and(shutdown);
+            if let Err(e) = result {
+                errors::exit(&e);
+            }
+        }
+        _ = tokio::signal::ctrl_c() => {
+            if let Err(e) = driver.shutdown() {
+                errors::print(&e);
+            }
+            eprintln!("Interrupted");
+            std::process::exit(INTERRUPTED_EXIT_CODE);
         }
-        write_invoice_pdf(&invoice, &cli).await?;
     }
-    kill_chrome(&mut chrome_process)?;
-    Ok(())
 }
@@ -0,0 +1,538 @@
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use chrono::{DateTime, FixedOffset, format::StrftimeItems};
+use clap::{Args, ValueEnum};
+use invoice_pdf::{Invoice, error::AddContext};
+use serde::Deserialize;
+
+/// What to do when an output file already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverwritePolicy {
+    /// Replace the existing file
+    Overwrite,
+    /// Leave the existing file alone and do not write the new one
+    Skip,
+    /// Write the new file next to the existing one as `name-1.ext`, `name-2.ext`, ...
+    Suffix,
+}
+
+/// Arguments controlling where rendered invoices are written
+#[derive(Debug, Args)]
+pub struct Output {
+    /// Path to the directory where outputs should be saved. Writes to stdout if omitted
+    #[arg(short, long)]
+    pub out: Option<PathBuf>,
+
+    /// Pattern for the path of each file inside `--out`. Placeholders are `{id}`, `{acct_id}`,
+    /// `{purchase_order}`, `{bill_to}`, `{sender}`, `{created}` and `{due}`. Dates accept a
+    /// strftime format such as `{created:%Y-%m}`. `/` in the pattern creates subdirectories,
+    /// and the pattern cannot start with one. Defaults to `{id}` with the extension of the output
+    #[arg(short, long)]
+    pub name: Option<String>,
+
+    /// What to do when a file with the same name already exists. Defaults to overwrite
+    #[arg(long, value_enum)]
+    pub overwrite: Option<OverwritePolicy>,
+
+    /// Write a JSON report of every invoice processed to this file, or to stderr if given
+    /// without a path or as `-`
+    #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = "-")]
+    pub report: Option<PathBuf>,
+}
+
+/// How `render --bundle` combines the printed invoices
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BundleFormat {
+    /// One PDF with every invoice in order and a bookmark per invoice id
+    Merged,
+    /// A ZIP archive with one PDF per invoice, named by `--name`
+    Zip,
+}
+
+impl From<BundleFormat> for invoice_pdf::bundle::BundleFormat {
+    fn from(value: BundleFormat) -> Self {
+        match value {
+            BundleFormat::Merged => Self::Merged,
+            BundleFormat::Zip => Self::Zip,
+        }
+    }
+}
+
+/// Arguments for combining a batch of printed invoices into a single file
+#[derive(Debug, Args)]
+pub struct Bundle {
+    /// Combine every invoice into a single file instead of writing one file per invoice
+    #[arg(long, value_enum, conflicts_with = "out")]
+    pub bundle: Option<BundleFormat>,
+
+    /// Path of the combined file. Writes to stdout if omitted
+    #[arg(long, requires = "bundle")]
+    pub bundle_file: Option<PathBuf>,
+}
+
+impl Bundle {
+    /// Write the combined `entries` to `--bundle-file` or stdout
+    pub fn write(
+        &self,
+        format: BundleFormat,
+        entries: &[invoice_pdf::bundle::BundleEntry],
+    ) -> Result<(), invoice_pdf::Error> {
+        let data = invoice_pdf::bundle::bundle(entries, format.into())?;
+        let Some(path) = &self.bundle_file else {
+            return crate::commands::write_stdout(&data).add_context("printing bundle to stdout");
+        };
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .map_err(invoice_pdf::Error::from)
+                .add_context(&format!(
+                    "creating bundle directory '{}'",
+                    parent.to_string_lossy()
+                ))?;
+        }
+        fs::write(path, data)
+            .map_err(invoice_pdf::Error::from)
+            .add_context(&format!("writing bundle '{}'", path.to_string_lossy()))
+    }
+}
+
+/// Where the output for a single invoice ended up
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Written {
+    /// Written to this file
+    File(PathBuf),
+    /// Not written because this file already exists and `--overwrite skip` was given
+    Skipped(PathBuf),
+    /// Printed to stdout
+    Stdout,
+    /// Added to the bundle written to this file, or to stdout if `None`
+    Bundle(Option<PathBuf>),
+}
+
+/// A value from the invoice that can appear in a name pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Id,
+    AcctId,
+    PurchaseOrder,
+    BillTo,
+    Sender,
+    Created,
+    Due,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "id" => Some(Self::Id),
+            "acct_id" => Some(Self::AcctId),
+            "purchase_order" => Some(Self::PurchaseOrder),
+            "bill_to" => Some(Self::BillTo),
+            "sender" => Some(Self::Sender),
+            "created" => Some(Self::Created),
+            "due" => Some(Self::Due),
+            _ => None,
+        }
+    }
+
+    fn is_date(self) -> bool {
+        matches!(self, Self::Created | Self::Due)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Field(Field, Option<String>),
+}
+
+/// A parsed `--name` pattern such as `{acct_id}/{created:%Y-%m}/{id}.pdf`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NameTemplate {
+    segments: Vec<Segment>,
+}
+
+/// Device names Windows reserves in every directory, with or without an extension
+const WINDOWS_RESERVED: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Whether Windows treats `component` as a device rather than a file, such as `nul` or
+/// `CON.pdf`
+fn is_windows_reserved(component: &str) -> bool {
+    let stem = component.split('.').next().unwrap_or_default().trim_end();
+    WINDOWS_RESERVED
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(stem))
+}
+
+/// Make a value from an invoice safe to use as a single path component. Values that Windows
+/// reserves as device names get a leading `_`
+fn sanitize(value: &str) -> String {
+    let cleaned: String = value
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    if cleaned.is_empty() || cleaned.chars().all(|c| c == '.') {
+        "_".repeat(cleaned.len().max(1))
+    } else if is_windows_reserved(&cleaned) {
+        format!("_{cleaned}")
+    } else {
+        cleaned
+    }
+}
+
+fn format_date(date: &DateTime<FixedOffset>, format: Option<&str>) -> String {
+    date.format(format.unwrap_or("%Y-%m-%d")).to_string()
+}
+
+impl NameTemplate {
+    /// Parse a name pattern, checking that it is a relative path and that every placeholder and
+    /// date format is valid
+    pub fn parse(pattern: &str) -> Result<Self, invoice_pdf::Error> {
+        let context = format!("parsing name pattern '{pattern}'");
+        if pattern.starts_with(['/', '\\']) || Path::new(pattern).has_root() {
+            return Err(invoice_pdf::Error::from(String::from(
+                "name pattern must be relative to the output directory",
+            )))
+            .add_context(&context);
+        }
+        let mut segments = Vec::new();
+        let mut rest = pattern;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                segments.push(Segment::Literal(rest[..start].to_string()));
+            }
+            let Some(end) = rest[start..].find('}') else {
+                return Err(invoice_pdf::Error::from(String::from(
+                    "unclosed '{' in name pattern",
+                )))
+                .add_context(&context);
+            };
+            let placeholder = &rest[start + 1..start + end];
+            let (name, format) = match placeholder.split_once(':') {
+                Some((name, format)) => (name, Some(format.to_string())),
+                None => (placeholder, None),
+            };
+            let field = Field::parse(name)
+                .ok_or_else(|| {
+                    invoice_pdf::Error::from(format!("unknown placeholder '{{{name}}}'"))
+                })
+                .add_context(&context)?;
+            if let Some(format) = &format {
+                if !field.is_date() {
+                    return Err(invoice_pdf::Error::from(format!(
+                        "placeholder '{{{name}}}' does not accept a format"
+                    )))
+                    .add_context(&context);
+                }
+                if StrftimeItems::new(format).any(|i| i == chrono::format::Item::Error) {
+                    return Err(invoice_pdf::Error::from(format!(
+                        "'{format}' is not a valid date format"
+                    )))
+                    .add_context(&context);
+                }
+            }
+            segments.push(Segment::Field(field, format));
+            rest = &rest[start + end + 1..];
+        }
+        if rest.contains('}') {
+            return Err(invoice_pdf::Error::from(String::from(
+                "unmatched '}' in name pattern",
+            )))
+            .add_context(&context);
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+        Ok(Self { segments })
+    }
+
+    /// The pattern used when `--name` is not given: the invoice id with `extension`
+    pub fn default_for(extension: &str) -> Self {
+        Self {
+            segments: vec![
+                Segment::Field(Field::Id, None),
+                Segment::Literal(format!(".{extension}")),
+            ],
+        }
+    }
+
+    /// Build the relative path for `invoice`
+    ///
+    /// Invoice values are sanitized so they can never add directories or escape the output
+    /// directory. Missing optional values are written as `unknown`. A path with a part Windows
+    /// reserves as a device name, such as `nul/1.pdf`, is an error.
+    pub fn render(&self, invoice: &Invoice) -> Result<PathBuf, invoice_pdf::Error> {
+        let mut rendered = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(literal) => rendered.push_str(literal),
+                Segment::Field(field, format) => {
+                    let value = match field {
+                        Field::Id => invoice.id(),
+                        Field::AcctId => invoice.acct_id().clone().unwrap_or_default(),
+                        Field::PurchaseOrder => {
+                            invoice.purchase_order().clone().unwrap_or_default()
+                        }
+                        Field::BillTo => invoice.bill_to().name().to_string(),
+                        Field::Sender => invoice.sender().name().to_string(),
+                        Field::Created => {
+                            format_date(invoice.created_datetime(), format.as_deref())
+                        }
+                        Field::Due => format_date(invoice.net_due_datetime(), format.as_deref()),
+                    };
+                    if value.trim().is_empty() {
+                        rendered.push_str("unknown");
+                    } else {
+                        rendered.push_str(&sanitize(&value));
+                    }
+                }
+            }
+        }
+
+        let path = PathBuf::from(rendered);
+        if path
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+            || path.as_os_str().is_empty()
+        {
+            return Err(invoice_pdf::Error::from(format!(
+                "'{}' is not a relative path inside the output directory",
+                path.to_string_lossy()
+            )))
+            .add_context(&format!("naming output for invoice {}", invoice.id()));
+        }
+        if let Some(reserved) = path
+            .iter()
+            .map(|c| c.to_string_lossy())
+            .find(|c| is_windows_reserved(c))
+        {
+            return Err(invoice_pdf::Error::from(format!(
+                "'{reserved}' is a reserved file name on Windows"
+            )))
+            .add_context(&format!("naming output for invoice {}", invoice.id()));
+        }
+        Ok(path)
+    }
+}
+
+/// The name of `invoice` inside a bundle: its id for a merged PDF, or its rendered name pattern
+/// with `/` separators for a ZIP entry
+pub fn bundle_entry_name(
+    format: BundleFormat,
+    template: &NameTemplate,
+    invoice: &Invoice,
+) -> Result<String, invoice_pdf::Error> {
+    match format {
+        BundleFormat::Merged => Ok(invoice.id()),
+        BundleFormat::Zip => Ok(template
+            .render(invoice)?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/")),
+    }
+}
+
+/// Pick the path to write to under `policy`, or `None` if the file should be skipped
+fn resolve_existing(path: &Path, policy: OverwritePolicy) -> Option<PathBuf> {
+    if !path.exists() {
+        return Some(path.to_path_buf());
+    }
+    match policy {
+        OverwritePolicy::Overwrite => Some(path.to_path_buf()),
+        OverwritePolicy::Skip => None,
+        OverwritePolicy::Suffix => {
+            let stem = path
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let extension = path
+                .extension()
+                .map(|e| format!(".{}", e.to_string_lossy()))
+                .unwrap_or_default();
+            (1..)
+                .map(|n| path.with_file_name(format!("{stem}-{n}{extension}")))
+                .find(|candidate| !candidate.exists())
+        }
+    }
+}
+
+impl Output {
+    /// The name pattern to use, falling back to the invoice id with `extension`
+    pub fn template(&self, extension: &str) -> Result<NameTemplate, invoice_pdf::Error> {
+        match &self.name {
+            Some(name) => NameTemplate::parse(name),
+            None => Ok(NameTemplate::default_for(extension)),
+        }
+    }
+
+    /// Write `data` for `invoice` to the file named by the pattern inside `--out`, creating any
+    /// missing directories, or to stdout if `--out` was not given
+    pub fn write(
+        &self,
+        template: &NameTemplate,
+        invoice: &Invoice,
+        data: &[u8],
+    ) -> Result<Written, invoice_pdf::Error> {
+        let Some(out) = &self.out else {
+            crate::commands::write_stdout(data).add_context("printing to stdout")?;
+            return Ok(Written::Stdout);
+        };
+        let path = out.join(template.render(invoice)?);
+        let Some(path) =
+            resolve_existing(&path, self.overwrite.unwrap_or(OverwritePolicy::Overwrite))
+        else {
+            return Ok(Written::Skipped(path));
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(invoice_pdf::Error::from)
+                .add_context(&format!(
+                    "creating output directory '{}'",
+                    parent.to_string_lossy()
+                ))?;
+        }
+        fs::write(&path, data)
+            .map_err(invoice_pdf::Error::from)
+            .add_context(&format!("writing '{}'", path.to_string_lossy()))?;
+        Ok(Written::File(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use invoice_pdf::{InvoiceBuilder, PartyBuilder};
+
+    use super::*;
+
+    fn invoice(id: &str, acct_id: Option<&str>) -> Invoice {
+        let mut builder = InvoiceBuilder::default()
+            .id(id)
+            .created_datetime(chrono::Utc.with_ymd_and_hms(2026, 2, 9, 12, 0, 0).unwrap())
+            .sender(PartyBuilder::default().name("Sender").build().unwrap())
+            .bill_to(PartyBuilder::default().name("Bob's Shop").build().unwrap());
+        if let Some(acct_id) = acct_id {
+            builder = builder.acct_id(acct_id);
+        }
+        builder.build().unwrap()
+    }
+
+    #[test]
+    fn test_render_template() {
+        let template = NameTemplate::parse("{acct_id}/{created:%Y-%m}/{id}.pdf").unwrap();
+        assert_eq!(
+            template.render(&invoice("42", Some("C100"))).unwrap(),
+            PathBuf::from("C100/2026-02/42.pdf")
+        );
+        assert_eq!(
+            template.render(&invoice("42", None)).unwrap(),
+            PathBuf::from("unknown/2026-02/42.pdf")
+        );
+        assert_eq!(
+            NameTemplate::default_for("html")
+                .render(&invoice("42", None))
+                .unwrap(),
+            PathBuf::from("42.html")
+        );
+    }
+
+    #[test]
+    fn test_render_sanitizes_values() {
+        let template = NameTemplate::parse("{acct_id}/{id}.pdf").unwrap();
+        assert_eq!(
+            template.render(&invoice("2026/01:7", Some(".."))).unwrap(),
+            PathBuf::from("__/2026_01_7.pdf")
+        );
+        assert!(
+            NameTemplate::parse("../{id}.pdf")
+                .unwrap()
+                .render(&invoice("1", None))
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(NameTemplate::parse("{nope}.pdf").is_err());
+        assert!(NameTemplate::parse("{id.pdf").is_err());
+        assert!(NameTemplate::parse("{id}}.pdf").is_err());
+        assert!(NameTemplate::parse("{id:%Y}.pdf").is_err());
+        assert!(NameTemplate::parse("{created:%Q}.pdf").is_err());
+        for absolute in ["/srv/{id}.pdf", "\\{id}.pdf"] {
+            let e = NameTemplate::parse(absolute).unwrap_err();
+            assert!(
+                e.to_string()
+                    .ends_with("name pattern must be relative to the output directory"),
+                "{e}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_windows_reserved_names() {
+        let template = NameTemplate::parse("{acct_id}/{id}.pdf").unwrap();
+        assert_eq!(
+            template.render(&invoice("con", Some("LPT1"))).unwrap(),
+            PathBuf::from("_LPT1/_con.pdf")
+        );
+        assert_eq!(
+            template.render(&invoice("console", Some("nul.x"))).unwrap(),
+            PathBuf::from("_nul.x/console.pdf")
+        );
+        let e = NameTemplate::parse("aux/{id}.pdf")
+            .unwrap()
+            .render(&invoice("1", None))
+            .unwrap_err();
+        assert!(
+            e.to_string()
+                .ends_with("'aux' is a reserved file name on Windows"),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn test_overwrite_policies() {
+        let dir = std::env::temp_dir().join(format!("invoice-pdf-output-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let template = NameTemplate::parse("{acct_id}/{id}.pdf").unwrap();
+        let inv = invoice("1", Some("C1"));
+        let output = |overwrite| Output {
+            out: Some(dir.clone()),
+            name: None,
+            overwrite: Some(overwrite),
+            report: None,
+        };
+
+        let first = output(OverwritePolicy::Overwrite)
+            .write(&template, &inv, b"one")
+            .unwrap();
+        assert_eq!(first, Written::File(dir.join("C1/1.pdf")));
+        let skipped = output(OverwritePolicy::Skip)
+            .write(&template, &inv, b"two")
+            .unwrap();
+        assert_eq!(skipped, Written::Skipped(dir.join("C1/1.pdf")));
+        let suffixed = output(OverwritePolicy::Suffix)
+            .write(&template, &inv, b"three")
+            .unwrap();
+        assert_eq!(suffixed, Written::File(dir.join("C1/1-1.pdf")));
+        output(OverwritePolicy::Overwrite)
+            .write(&template, &inv, b"four")
+            .unwrap();
+        assert_eq!(fs::read(dir.join("C1/1.pdf")).unwrap(), b"four");
+        assert_eq!(fs::read(dir.join("C1/1-1.pdf")).unwrap(), b"three");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,253 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use invoice_pdf::{Invoice, error::AddContext};
+use serde_json::{Value, json};
+
+use crate::{errors, output::Written};
+
+/// Collects the outcome of every invoice in a batch and writes them as a JSON report
+///
+/// Without a report target, failures and skipped files are printed to stderr as they happen. When
+/// the report goes to stderr those messages are left out so stderr holds only the report.
+pub struct Report {
+    target: Option<PathBuf>,
+    started: Instant,
+    entries: Vec<Value>,
+    failed: usize,
+}
+
+fn path_string(path: &Path) -> String {
+    path.to_string_lossy().to_string()
+}
+
+impl Report {
+    /// Start a report that will be written to `target`, where `-` means stderr
+    pub fn new(target: Option<PathBuf>) -> Self {
+        Self {
+            target,
+            started: Instant::now(),
+            entries: Vec::new(),
+            failed: 0,
+        }
+    }
+
+    fn to_stderr(&self) -> bool {
+        self.target.as_deref() == Some(Path::new("-"))
+    }
+
+    /// Record the outcome of handling one invoice
+    ///
+    /// # Arguments
+    /// * `invoice` - The invoice handled, or `None` if it could not be read. The id of an invoice
+    ///   that could not be read is then taken from the error, if known
+    /// * `started` - When work on the invoice started
+    /// * `result` - Where the output went and how many bytes it had, or the error that stopped it
+    pub fn record(
+        &mut self,
+        invoice: Option<&Invoice>,
+        started: Instant,
+        result: Result<(Written, usize), invoice_pdf::Error>,
+    ) {
+        self.record_elapsed(invoice, started.elapsed(), result);
+    }
+
+    /// Record the outcome of handling one invoice that took `elapsed`, for invoices whose outcome
+    /// is only known after later invoices have been handled
+    pub fn record_elapsed(
+        &mut self,
+        invoice: Option<&Invoice>,
+        elapsed: Duration,
+        result: Result<(Written, usize), invoice_pdf::Error>,
+    ) {
+        let id = invoice.map(Invoice::id).or_else(|| {
+            let e = result.as_ref().err()?;
+            e.invoice_id().map(String::from)
+        });
+        let (status, path, bytes, error) = match result {
+            Ok((Written::File(path), bytes)) => ("written", Some(path_string(&path)), bytes, None),
+            Ok((Written::Bundle(path), bytes)) => {
+                ("bundled", path.as_deref().map(path_string), bytes, None)
+            }
+            Ok((Written::Stdout, bytes)) => ("written", None, bytes, None),
+            Ok((Written::Skipped(path), _)) => {
+                if !self.to_stderr() {
+                    eprintln!(
+                        "Skipping invoice '{}' because '{}' already exists",
+                        invoice.map(Invoice::id).unwrap_or_default(),
+                        path.to_string_lossy()
+                    );
+                }
+                ("skipped", Some(path_string(&path)), 0, None)
+            }
+            Err(e) => {
+                if self.to_stderr() {
+                    errors::record_failure(&e);
+                } else {
+                    errors::print(&e);
+                }
+                self.failed += 1;
+                ("failed", None, 0, Some(format!("{e:?}")))
+            }
+        };
+        self.entries.push(json!({
+            "id": id,
+            "status": status,
+            "path": path,
+            "bytes": bytes,
+            "total": invoice.map(|i| i.rounding().total.format(&i.total())),
+            "net_due": invoice.map(|i| i.rounding().total.format(&i.net_due())),
+            "adjustments": invoice.map(Invoice::rounding_adjustments).unwrap_or_default(),
+            "elapsed_ms": elapsed.as_millis() as u64,
+            "error": error,
+        }));
+    }
+
+    /// The report as JSON
+    pub fn to_json(&self) -> Value {
+        json!({
+            "invoices": self.entries,
+            "succeeded": self.entries.len() - self.failed,
+            "failed": self.failed,
+            "elapsed_ms": self.started.elapsed().as_millis() as u64,
+        })
+    }
+
+    /// Write the report, if one was requested, and turn any failures into the error the command
+    /// exits with
+    pub fn finish(self, action: &str) -> Result<(), invoice_pdf::Error> {
+        if let Some(target) = &self.target {
+            let report = serde_json::to_string_pretty(&self.to_json())
+                .map_err(invoice_pdf::Error::from)
+                .add_context("serializing batch report")?;
+            if self.to_stderr() {
+                eprintln!("{report}");
+            } else {
+                fs::write(target, report + "\n")
+                    .map_err(invoice_pdf::Error::from)
+                    .add_context(&format!(
+                        "writing batch report to '{}'",
+                        path_string(target)
+                    ))?;
+            }
+        }
+
+        if self.failed == 0 {
+            return Ok(());
+        }
+        Err(invoice_pdf::Error::from(format!(
+            "{} invoice(s) could not be {action}",
+            self.failed
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use invoice_pdf::{InvoiceBuilder, LineItemBuilder, PartyBuilder};
+
+    use super::*;
+    use crate::cli::{Input, InputFormat};
+
+    #[test]
+    fn test_report_entries() {
+        let invoice = InvoiceBuilder::default()
+            .id("1")
+            .sender(PartyBuilder::default().name("S").build().unwrap())
+            .bill_to(PartyBuilder::default().name("B").build().unwrap())
+            .add_line(
+                LineItemBuilder::default()
+                    .sku("A")
+                    .title("T")
+                    .quantity(2)
+                    .price(3)
+                    .build()
+                    .unwrap(),
+            )
+            .paid(1)
+            .build()
+            .unwrap();
+
+        let mut report = Report::new(Some(PathBuf::from("-")));
+        report.record(
+            Some(&invoice),
+            Instant::now(),
+            Ok((Written::File(PathBuf::from("out/1.pdf")), 42)),
+        );
+        report.record(
+            None,
+            Instant::now(),
+            Err(invoice_pdf::Error::from(String::from("bad line"))),
+        );
+
+        let json = report.to_json();
+        assert_eq!(json["succeeded"], 1);
+        assert_eq!(json["failed"], 1);
+        let written = &json["invoices"][0];
+        assert_eq!(written["id"], "1");
+        assert_eq!(written["status"], "written");
+        assert_eq!(written["path"], "out/1.pdf");
+        assert_eq!(written["bytes"], 42);
+        assert_eq!(written["total"], "6.00");
+        assert_eq!(written["net_due"], "5.00");
+        assert!(written["error"].is_null());
+        assert_eq!(written["adjustments"], json!([]));
+        let failed = &json["invoices"][1];
+        assert!(failed["id"].is_null());
+        assert_eq!(failed["status"], "failed");
+        assert_eq!(failed["error"], "bad line");
+
+        // Amounts have the invoice's total places rather than always two
+        let whole: Invoice = serde_json::from_value(json!({
+            "id": "2",
+            "created_datetime": "2026-01-01T00:00:00Z",
+            "sender": {"name": "S"},
+            "bill_to": {"name": "B"},
+            "line_items": [{"sku": "A", "title": "T", "quantity": "1", "price": "2.5"}],
+            "paid": "1",
+            "post_scripts": [],
+            "rounding": {"total": {"places": 0, "mode": "half_even"}},
+        }))
+        .unwrap();
+        report.record(Some(&whole), Instant::now(), Ok((Written::Stdout, 1)));
+        let json = report.to_json();
+        assert_eq!(json["invoices"][2]["total"], "2");
+        assert_eq!(json["invoices"][2]["net_due"], "1");
+
+        assert!(report.finish("printed").is_err());
+    }
+
+    #[test]
+    fn test_failed_invoice_keeps_its_id() {
+        let path =
+            std::env::temp_dir().join(format!("invoice-pdf-report-{}.ndjson", std::process::id()));
+        fs::write(
+            &path,
+            "{\"id\": \"9\", \"created_datetime\": \"2026-01-01T00:00:00Z\"}\n{\"created_datetime\": 1}\n",
+        )
+        .unwrap();
+        let input = Input {
+            data: Some(path.clone()),
+            format: Some(InputFormat::Ndjson),
+            defaults: Default::default(),
+            rules: Default::default(),
+            overdue_as_of: None,
+            late_fees: Default::default(),
+        };
+
+        let mut report = Report::new(Some(PathBuf::from("-")));
+        for invoice in input.invoices().unwrap() {
+            report.record(None, Instant::now(), invoice.map(|_| (Written::Stdout, 0)));
+        }
+        fs::remove_file(&path).unwrap();
+
+        let json = report.to_json();
+        assert_eq!(json["failed"], 2);
+        assert_eq!(json["invoices"][0]["id"], "9");
+        assert_eq!(json["invoices"][0]["status"], "failed");
+        assert!(json["invoices"][1]["id"].is_null());
+    }
+}
@@ -0,0 +1,316 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    Json, Router,
+    body::Bytes,
+    extract::State,
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+};
+use invoice_pdf::{
+    Invoice,
+    defaults::InvoiceDefaults,
+    error::{AddContext, ErrorKind},
+    pool::SessionPool,
+    validation::{Issue, ValidationRules},
+};
+use serde_json::json;
+
+use crate::{browser::LazyChromeDriver, config::Config};
+
+/// State shared by every request handler
+#[derive(Clone)]
+struct AppState {
+    pool: Arc<SessionPool>,
+    driver: LazyChromeDriver,
+    defaults: Arc<InvoiceDefaults>,
+    rules: Arc<ValidationRules>,
+}
+
+/// An error response with a JSON body of the form `{"error": "..."}`
+struct ApiError {
+    status: StatusCode,
+    error: Box<invoice_pdf::Error>,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, error: invoice_pdf::Error) -> Self {
+        Self {
+            status,
+            error: Box::new(error),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (
+            self.status,
+            Json(json!({ "error": format!("{:?}", self.error) })),
+        )
+            .into_response()
+    }
+}
+
+fn parse_invoice(defaults: &InvoiceDefaults, body: &[u8]) -> Result<Invoice, ApiError> {
+    serde_json::from_slice(body)
+        .map_err(invoice_pdf::Error::from)
+        .and_then(|value| defaults.invoice_from_value(value))
+        .add_context("parsing invoice JSON from request body")
+        .map_err(|e| ApiError::new(StatusCode::BAD_REQUEST, e))
+}
+
+/// Check the invoice against the validation rules and that its logo and payment QR code can be
+/// drawn, returning its warnings
+fn check(invoice: &Invoice, rules: &ValidationRules) -> Result<Vec<Issue>, ApiError> {
+    invoice
+        .validate_with(rules)
+        .into_result()
+        .add_context(&format!("validating invoice {}", invoice.id()))
+        .and_then(|warnings| invoice.check_assets().map(|_| warnings))
+        .map_err(|e| ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, e))
+}
+
+fn render_html(invoice: &Invoice) -> Result<String, ApiError> {
+    invoice
+        .render_html()
+        .add_context(&format!("rendering invoice {}", invoice.id()))
+        .map_err(|e| ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, e))
+}
+
+/// Report a failure to start or use the browser as unavailable when retrying may succeed, and as
+/// a server error otherwise
+fn browser_error(e: invoice_pdf::Error) -> ApiError {
+    let status = if e.kind.is_transient() {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    ApiError::new(status, e)
+}
+
+async fn health() -> Json<serde_json::Value> {
+    Json(json!({ "status": "ok" }))
+}
+
+async fn invoice_html(State(state): State<AppState>, body: Bytes) -> Result<Response, ApiError> {
+    let invoice = parse_invoice(&state.defaults, &body)?;
+    check(&invoice, &state.rules)?;
+    let html = render_html(&invoice)?;
+    Ok(([(header::CONTENT_TYPE, "text/html; charset=utf-8")], html).into_response())
+}
+
+async fn invoice_pdf(State(state): State<AppState>, body: Bytes) -> Result<Response, ApiError> {
+    let invoice = parse_invoice(&state.defaults, &body)?;
+    check(&invoice, &state.rules)?;
+    render_html(&invoice)?;
+
+    let driver = state.driver.clone();
+    tokio::task::spawn_blocking(move || driver.ensure_started())
+        .await
+        .map_err(|e| invoice_pdf::Error::from(e.to_string()))
+        .and_then(|started| started)
+        .map_err(browser_error)?;
+
+    let pdf = state
+        .pool
+        .generate_pdf_unchecked(&invoice)
+        .await
+        .add_context(&format!("printing invoice {}", invoice.id()))
+        .map_err(browser_error)?;
+    Ok(([(header::CONTENT_TYPE, "application/pdf")], pdf).into_response())
+}
+
+async fn invoice_validate(State(state): State<AppState>, body: Bytes) -> Response {
+    let result = parse_invoice(&state.defaults, &body).and_then(|invoice| {
+        let warnings = check(&invoice, &state.rules)?;
+        render_html(&invoice)?;
+        Ok((invoice, warnings))
+    });
+    match result {
+        Ok((invoice, warnings)) => Json(json!({
+            "valid": true,
+            "id": invoice.id(),
+            "total": invoice.rounding().total.format(&invoice.total()),
+            "net_due": invoice.rounding().total.format(&invoice.net_due()),
+            "warnings": warnings,
+            "adjustments": invoice.rounding_adjustments(),
+        }))
+        .into_response(),
+        Err(e) => {
+            let issues = match &e.error.kind {
+                ErrorKind::Validation(issues) => issues.as_slice(),
+                _ => &[],
+            };
+            (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(json!({
+                    "valid": false,
+                    "error": format!("{:?}", e.error),
+                    "issues": issues,
+                })),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// Build the HTTP routes of the rendering service
+///
+/// - `GET /health` reports that the service is up
+/// - `POST /invoices/pdf` prints the invoice JSON in the body and responds with the PDF
+/// - `POST /invoices/html` responds with the rendered HTML of the invoice
+/// - `POST /invoices/validate` parses, checks and renders the invoice and reports whether that
+///   worked, with any validation warnings or errors
+///
+/// Invoices that break a validation rule with error severity are rejected with status 422.
+fn router(
+    pool: Arc<SessionPool>,
+    driver: LazyChromeDriver,
+    defaults: Arc<InvoiceDefaults>,
+    rules: Arc<ValidationRules>,
+) -> Router {
+    Router::new()
+        .route("/health", get(health))
+        .route("/invoices/pdf", post(invoice_pdf))
+        .route("/invoices/html", post(invoice_html))
+        .route("/invoices/validate", post(invoice_validate))
+        .with_state(AppState {
+            pool,
+            driver,
+            defaults,
+            rules,
+        })
+}
+
+/// Serve the rendering endpoints on `bind` until the process is stopped
+///
+/// Chromedriver is started with the first PDF request and PDFs are printed through a pool of at
+/// most `sessions` browser sessions. Posted invoices are merged over the config defaults, checked
+/// with the config validation rules and printed with the config page settings.
+pub async fn serve(
+    bind: SocketAddr,
+    sessions: usize,
+    config: &Config,
+    driver: &LazyChromeDriver,
+) -> Result<(), invoice_pdf::Error> {
+    let listener = tokio::net::TcpListener::bind(bind)
+        .await
+        .map_err(invoice_pdf::Error::from)
+        .add_context(&format!("binding http server to {bind}"))?;
+    eprintln!("Listening on http://{bind}");
+    let pool = Arc::new(SessionPool::new(sessions).with_page_settings(config.page));
+    let defaults = Arc::new(config.invoice_defaults());
+    let rules = Arc::new(config.validation.clone());
+    axum::serve(listener, router(pool, driver.clone(), defaults, rules))
+        .await
+        .map_err(invoice_pdf::Error::from)
+        .add_context("serving http requests")
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    const INVOICE: &str = r#"{"id":"7","created_datetime":"2026-02-10T12:00:00+00:00","net_due_datetime":"2026-02-10T12:00:00+00:00","bill_to":{"name":"B"},"sender":{"name":"S"},"line_items":[{"sku":"A","title":"T","quantity":"2","price":"1.50"}],"paid":"1","post_scripts":[]}"#;
+
+    fn app() -> Router {
+        router(
+            Arc::new(SessionPool::new(1)),
+            LazyChromeDriver::default(),
+            Arc::default(),
+            Arc::default(),
+        )
+    }
+
+    async fn post(uri: &str, body: &str) -> (StatusCode, String) {
+        let response = app()
+            .oneshot(
+                Request::post(uri)
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(body.to_string()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_health() {
+        let response = app()
+            .oneshot(Request::get("/health").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_html() {
+        let (status, body) = post("/invoices/html", INVOICE).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("<html"), "{body}");
+
+        let (status, body) = post("/invoices/html", "{").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let error: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert!(
+            error["error"]
+                .as_str()
+                .unwrap()
+                .contains("parsing invoice JSON")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate() {
+        let (status, body) = post("/invoices/validate", INVOICE).await;
+        assert_eq!(status, StatusCode::OK);
+        let report: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(report["valid"], true);
+        assert_eq!(report["total"], "3.00");
+        assert_eq!(report["net_due"], "2.00");
+        assert_eq!(report["warnings"], json!([]));
+
+        let precise = INVOICE.replace(
+            r#""paid":"1""#,
+            r#""paid":"1","rounding":{"total":{"places":3,"mode":"half_even"}}"#,
+        );
+        let (status, body) = post("/invoices/validate", &precise).await;
+        assert_eq!(status, StatusCode::OK);
+        let report: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(report["total"], "3.000");
+        assert_eq!(report["net_due"], "2.000");
+
+        let overpaid = INVOICE.replace(r#""paid":"1""#, r#""paid":"5""#);
+        let (status, body) = post("/invoices/validate", &overpaid).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let report: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(report["valid"], false);
+        assert_eq!(report["issues"][0]["rule"], "overpaid");
+        assert_eq!(report["issues"][0]["path"], "paid");
+
+        let missing_logo = INVOICE.replace(r#""paid""#, r#""logo":"./no-such-logo.png","paid""#);
+        let (status, body) = post("/invoices/validate", &missing_logo).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let report: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(report["valid"], false);
+        assert!(
+            report["error"].as_str().unwrap().contains("checking logo"),
+            "{report}"
+        );
+
+        let (status, body) = post("/invoices/validate", r#"{"id": "7"}"#).await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        let report: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(report["valid"], false);
+    }
+}
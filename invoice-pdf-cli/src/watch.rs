@@ -0,0 +1,285 @@
+use std::{
+    fs,
+    future::Future,
+    net::SocketAddr,
+    path::Path,
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use axum::{
+    Router,
+    extract::{Path as UrlPath, State},
+    http::StatusCode,
+    response::Html,
+    routing::get,
+};
+use clap::Args;
+use invoice_pdf::error::AddContext;
+
+use crate::{cli::Input, errors};
+
+/// How often the data file is checked for changes
+const POLL_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Polls `/version` and reloads the page when the invoices have been rebuilt
+const RELOAD_SCRIPT: &str = r#"<script>
+(function () {
+    var version = null;
+    setInterval(function () {
+        fetch("/version").then(function (r) { return r.text(); }).then(function (v) {
+            if (version !== null && v !== version) { location.reload(); }
+            version = v;
+        }).catch(function () {});
+    }, 500);
+})();
+</script>"#;
+
+/// Arguments for rebuilding outputs whenever the invoice data changes
+#[derive(Debug, Args)]
+pub struct Watch {
+    /// Rebuild whenever the `--data` file changes and serve a live reloading HTML preview.
+    /// Templates are compiled into the binary, so only the data file is watched
+    #[arg(long, requires = "data")]
+    pub watch: bool,
+
+    /// Address and port of the preview server started by `--watch`
+    #[arg(long, default_value = "127.0.0.1:8081", requires = "watch")]
+    pub preview: SocketAddr,
+}
+
+/// A single invoice in the preview
+struct Page {
+    title: String,
+    html: Result<String, String>,
+}
+
+/// What the preview server currently shows
+#[derive(Default)]
+struct Preview {
+    version: u64,
+    pages: Vec<Page>,
+    error: Option<String>,
+}
+
+type SharedPreview = Arc<RwLock<Preview>>;
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Add the live reload script to the end of an HTML document's body
+fn inject_reload(html: &str) -> String {
+    match html.rfind("</body>") {
+        Some(i) => format!("{}{RELOAD_SCRIPT}{}", &html[..i], &html[i..]),
+        None => format!("{html}{RELOAD_SCRIPT}"),
+    }
+}
+
+async fn index(State(preview): State<SharedPreview>) -> Html<String> {
+    let preview = preview.read().unwrap_or_else(|e| e.into_inner());
+    let mut body =
+        String::from("<!DOCTYPE html><html><head><title>Invoice preview</title></head><body>");
+    if let Some(error) = &preview.error {
+        body.push_str(&format!("<pre>{}</pre>", escape(error)));
+    }
+    body.push_str("<ul>");
+    for (i, page) in preview.pages.iter().enumerate() {
+        match &page.html {
+            Ok(_) => body.push_str(&format!(
+                "<li><a href=\"/invoices/{i}\">{}</a></li>",
+                escape(&page.title)
+            )),
+            Err(e) => body.push_str(&format!(
+                "<li>{}<pre>{}</pre></li>",
+                escape(&page.title),
+                escape(e)
+            )),
+        }
+    }
+    body.push_str("</ul></body></html>");
+    Html(inject_reload(&body))
+}
+
+async fn invoice(
+    State(preview): State<SharedPreview>,
+    UrlPath(index): UrlPath<usize>,
+) -> Result<Html<String>, (StatusCode, Html<String>)> {
+    let preview = preview.read().unwrap_or_else(|e| e.into_inner());
+    match preview.pages.get(index).map(|page| &page.html) {
+        Some(Ok(html)) => Ok(Html(inject_reload(html))),
+        Some(Err(e)) => Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Html(inject_reload(&format!("<pre>{}</pre>", escape(e)))),
+        )),
+        None => Err((
+            StatusCode::NOT_FOUND,
+            Html(inject_reload("<p>No invoice at this position</p>")),
+        )),
+    }
+}
+
+async fn version(State(preview): State<SharedPreview>) -> String {
+    preview
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .version
+        .to_string()
+}
+
+fn router(preview: SharedPreview) -> Router {
+    Router::new()
+        .route("/", get(index))
+        .route("/invoices/{index}", get(invoice))
+        .route("/version", get(version))
+        .with_state(preview)
+}
+
+/// Read the invoices and render each one for the preview
+fn build_preview(input: &Input, preview: &SharedPreview) {
+    let (pages, error) = match input.invoices() {
+        Ok(invoices) => (
+            invoices
+                .enumerate()
+                .map(|(i, invoice)| match invoice {
+                    Ok(invoice) => Page {
+                        title: invoice.id(),
+                        html: invoice.render_html().map_err(|e| format!("{e:?}")),
+                    },
+                    Err(e) => Page {
+                        title: format!("invoice {}", i + 1),
+                        html: Err(format!("{e:?}")),
+                    },
+                })
+                .collect(),
+            None,
+        ),
+        Err(e) => (Vec::new(), Some(format!("{e:?}"))),
+    };
+    let mut preview = preview.write().unwrap_or_else(|e| e.into_inner());
+    preview.pages = pages;
+    preview.error = error;
+    preview.version += 1;
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Run `rebuild` now and again every time the `--data` file changes, while serving a live
+/// reloading HTML preview of the invoices on `watch.preview`
+///
+/// Runs until the process is stopped. Errors from `rebuild` are printed and watching continues,
+/// so a half saved data file does not end the session.
+pub async fn watch<F, Fut>(
+    input: &Input,
+    watch: &Watch,
+    mut rebuild: F,
+) -> Result<(), invoice_pdf::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), invoice_pdf::Error>>,
+{
+    let Some(path) = &input.data else {
+        return Err(invoice_pdf::Error::from(String::from(
+            "--watch needs a --data file to watch",
+        )));
+    };
+
+    let preview = SharedPreview::default();
+    let listener = tokio::net::TcpListener::bind(watch.preview)
+        .await
+        .map_err(invoice_pdf::Error::from)
+        .add_context(&format!("binding preview server to {}", watch.preview))?;
+    let server = axum::serve(listener, router(preview.clone()));
+    tokio::spawn(async move {
+        if let Err(e) = server.await {
+            eprintln!("Preview server stopped: {e}");
+        }
+    });
+    eprintln!("Previewing on http://{}", watch.preview);
+
+    let mut last_modified = None;
+    loop {
+        let current = modified(path);
+        if current.is_some() && current != last_modified {
+            last_modified = current;
+            build_preview(input, &preview);
+            if let Err(e) = rebuild().await {
+                errors::print(&e);
+            }
+            eprintln!("Rebuilt from '{}'", path.to_string_lossy());
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn test_inject_reload() {
+        let html = inject_reload("<html><body><p>hi</p></body></html>");
+        assert!(html.ends_with(&format!("<p>hi</p>{RELOAD_SCRIPT}</body></html>")));
+        assert_eq!(
+            inject_reload("<p>hi</p>"),
+            format!("<p>hi</p>{RELOAD_SCRIPT}")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_preview_routes() {
+        let preview = SharedPreview::default();
+        {
+            let mut preview = preview.write().unwrap();
+            preview.version = 3;
+            preview.pages = vec![
+                Page {
+                    title: String::from("inv-1"),
+                    html: Ok(String::from("<html><body>one</body></html>")),
+                },
+                Page {
+                    title: String::from("invoice 2"),
+                    html: Err(String::from("missing <field>")),
+                },
+            ];
+        }
+
+        let get = |uri: &str| {
+            router(preview.clone()).oneshot(Request::get(uri).body(Body::empty()).unwrap())
+        };
+        let body = |response: axum::response::Response| async {
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            String::from_utf8(bytes.to_vec()).unwrap()
+        };
+
+        let index = body(get("/").await.unwrap()).await;
+        assert!(
+            index.contains("<a href=\"/invoices/0\">inv-1</a>"),
+            "{index}"
+        );
+        assert!(index.contains("missing &lt;field&gt;"), "{index}");
+
+        let page = get("/invoices/0").await.unwrap();
+        assert_eq!(page.status(), StatusCode::OK);
+        assert!(body(page).await.contains(RELOAD_SCRIPT));
+        assert_eq!(
+            get("/invoices/1").await.unwrap().status(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(
+            get("/invoices/5").await.unwrap().status(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(body(get("/version").await.unwrap()).await, "3");
+    }
+}
@@ -0,0 +1,293 @@
+//! Combine the PDFs of a batch of invoices into a single file.
+//!
+//! Mailing runs need every invoice in one file rather than one file per invoice. Two layouts are
+//! supported: a single merged PDF with one bookmark per invoice, or a ZIP archive holding each
+//! invoice's PDF as its own entry. Only available with the `bundle` feature.
+
+use std::io::{Cursor, Write};
+
+use lopdf::{Bookmark, Document, Object, ObjectId, dictionary};
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use crate::{Invoice, error::AddContext, generate_pdf};
+
+/// How a batch of invoice PDFs is combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundleFormat {
+    /// One PDF containing every invoice in order, with a bookmark for each
+    Merged,
+    /// A ZIP archive with one PDF entry per invoice
+    Zip,
+}
+
+/// A rendered PDF along with the name it is filed under in a bundle
+///
+/// For [`BundleFormat::Merged`] the name is the bookmark title, for [`BundleFormat::Zip`] it is
+/// the path of the entry inside the archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleEntry {
+    pub name: String,
+    pub pdf: Vec<u8>,
+}
+
+/// Page attributes a page may inherit from its ancestors in the page tree
+const INHERITABLE: [&[u8]; 4] = [b"Resources", b"MediaBox", b"CropBox", b"Rotate"];
+
+/// Copy any inherited page attributes onto the page itself, so the page keeps them once it is
+/// moved under a new parent
+fn inherit_attributes(doc: &mut Document, page_id: ObjectId) -> Result<(), crate::Error> {
+    let mut inherited = Vec::new();
+    let mut parent = doc
+        .get_dictionary(page_id)
+        .and_then(|page| page.get(b"Parent"))
+        .and_then(Object::as_reference)
+        .ok();
+    while let Some(parent_id) = parent {
+        let node = doc.get_dictionary(parent_id)?;
+        for key in INHERITABLE {
+            if let Ok(value) = node.get(key) {
+                inherited.push((key, value.clone()));
+            }
+        }
+        parent = node.get(b"Parent").and_then(Object::as_reference).ok();
+    }
+
+    let page = doc.get_dictionary_mut(page_id)?;
+    for (key, value) in inherited {
+        if !page.has(key) {
+            page.set(key, value);
+        }
+    }
+    Ok(())
+}
+
+/// Concatenate several PDFs into one, adding a top level bookmark at the first page of each
+///
+/// # Arguments
+/// * `entries` - The PDFs to merge, in order. Each entry's name is used as its bookmark title
+///
+/// # Errors
+/// - [`crate::Error`] if any entry is not a readable PDF or has no pages
+pub fn merge_pdfs(entries: &[BundleEntry]) -> Result<Vec<u8>, crate::Error> {
+    let mut merged = Document::with_version("1.5");
+    let pages_id = merged.new_object_id();
+    let mut kids = Vec::new();
+
+    for entry in entries {
+        let context = format!("merging pdf for '{}'", entry.name);
+        let mut doc = Document::load_mem(&entry.pdf)
+            .map_err(crate::Error::from)
+            .add_context(&context)?;
+        doc.renumber_objects_with(merged.max_id + 1);
+        merged.max_id = doc.max_id;
+
+        let pages: Vec<ObjectId> = doc.get_pages().into_values().collect();
+        let Some(first_page) = pages.first() else {
+            return Err(crate::Error::from(String::from("pdf has no pages"))).add_context(&context);
+        };
+        for page_id in &pages {
+            inherit_attributes(&mut doc, *page_id).add_context(&context)?;
+        }
+        merged.add_bookmark(
+            Bookmark::new(entry.name.clone(), [0.0, 0.0, 0.0], 0, *first_page),
+            None,
+        );
+        kids.extend(pages.iter().map(|id| Object::Reference(*id)));
+
+        for (id, mut object) in doc.objects {
+            match object.type_name().unwrap_or(b"") {
+                b"Catalog" | b"Pages" | b"Outlines" | b"Outline" | b"ObjStm" | b"XRef" => {}
+                b"Page" => {
+                    if let Object::Dictionary(page) = &mut object {
+                        page.set("Parent", pages_id);
+                    }
+                    merged.objects.insert(id, object);
+                }
+                _ => {
+                    merged.objects.insert(id, object);
+                }
+            }
+        }
+    }
+
+    let count = kids.len() as i64;
+    merged.objects.insert(
+        pages_id,
+        Object::Dictionary(dictionary! {
+            "Type" => "Pages",
+            "Kids" => kids,
+            "Count" => count,
+        }),
+    );
+    let catalog_id = merged.add_object(dictionary! {
+        "Type" => "Catalog",
+        "Pages" => pages_id,
+        "PageMode" => "UseOutlines",
+    });
+    if let Some(outline_id) = merged.build_outline() {
+        merged
+            .get_dictionary_mut(catalog_id)?
+            .set("Outlines", outline_id);
+    }
+    merged.trailer.set("Root", catalog_id);
+
+    let mut data = Vec::new();
+    merged
+        .save_to(&mut data)
+        .map_err(crate::Error::from)
+        .add_context("saving merged pdf")?;
+    Ok(data)
+}
+
+/// Write several PDFs into a ZIP archive
+///
+/// # Arguments
+/// * `entries` - The PDFs to store. Each entry's name is its path inside the archive
+///
+/// # Errors
+/// - [`crate::Error`] if two entries share a name or the archive cannot be written
+pub fn zip_pdfs(entries: &[BundleEntry]) -> Result<Vec<u8>, crate::Error> {
+    let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    for entry in entries {
+        let context = format!("adding '{}' to zip archive", entry.name);
+        archive
+            .start_file(&entry.name, options)
+            .map_err(crate::Error::from)
+            .add_context(&context)?;
+        archive
+            .write_all(&entry.pdf)
+            .map_err(crate::Error::from)
+            .add_context(&context)?;
+    }
+    Ok(archive
+        .finish()
+        .map_err(crate::Error::from)
+        .add_context("finishing zip archive")?
+        .into_inner())
+}
+
+/// Combine several PDFs into one file laid out as `format`
+pub fn bundle(entries: &[BundleEntry], format: BundleFormat) -> Result<Vec<u8>, crate::Error> {
+    match format {
+        BundleFormat::Merged => merge_pdfs(entries),
+        BundleFormat::Zip => zip_pdfs(entries),
+    }
+}
+
+/// Print every invoice to PDF with [`generate_pdf`] and combine them into one file
+///
+/// Bookmarks are titled with the invoice id and ZIP entries are named `{id}.pdf`, with any `/`
+/// or `\` in the id replaced by `_`. Requires chromedriver to be running, as for [`generate_pdf`].
+pub async fn generate_bundle(
+    invoices: &[Invoice],
+    format: BundleFormat,
+) -> Result<Vec<u8>, crate::Error> {
+    let mut entries = Vec::new();
+    for invoice in invoices {
+        let pdf = generate_pdf(invoice)
+            .await
+            .add_context(&format!("generating pdf for invoice {}", invoice.id()))?;
+        let name = match format {
+            BundleFormat::Merged => invoice.id(),
+            BundleFormat::Zip => format!("{}.pdf", invoice.id().replace(['/', '\\'], "_")),
+        };
+        entries.push(BundleEntry { name, pdf });
+    }
+    bundle(&entries, format)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use lopdf::Stream;
+
+    use super::*;
+
+    /// A minimal PDF with `pages` blank pages whose media box lives on the page tree root
+    fn blank_pdf(pages: usize) -> Vec<u8> {
+        let mut doc = Document::with_version("1.5");
+        let pages_id = doc.new_object_id();
+        let kids: Vec<Object> = (0..pages)
+            .map(|_| {
+                let content_id = doc.add_object(Stream::new(dictionary! {}, Vec::new()));
+                doc.add_object(dictionary! {
+                    "Type" => "Page",
+                    "Parent" => pages_id,
+                    "Contents" => content_id,
+                })
+                .into()
+            })
+            .collect();
+        doc.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => kids,
+                "Count" => pages as i64,
+                "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+            }),
+        );
+        let catalog_id = doc.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        doc.trailer.set("Root", catalog_id);
+        let mut data = Vec::new();
+        doc.save_to(&mut data).unwrap();
+        data
+    }
+
+    fn entries() -> Vec<BundleEntry> {
+        vec![
+            BundleEntry {
+                name: String::from("1"),
+                pdf: blank_pdf(2),
+            },
+            BundleEntry {
+                name: String::from("2"),
+                pdf: blank_pdf(1),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_merge_pdfs() {
+        let merged = Document::load_mem(&merge_pdfs(&entries()).unwrap()).unwrap();
+        let pages = merged.get_pages();
+        assert_eq!(pages.len(), 3);
+        for page_id in pages.values() {
+            assert!(merged.get_dictionary(*page_id).unwrap().has(b"MediaBox"));
+        }
+
+        let toc = merged.get_toc().unwrap();
+        let bookmarks: Vec<_> = toc.toc.iter().map(|t| (t.title.as_str(), t.page)).collect();
+        assert_eq!(bookmarks, vec![("1", 1), ("2", 3)]);
+    }
+
+    #[test]
+    fn test_merge_rejects_invalid_pdf() {
+        let mut entries = entries();
+        entries[1].pdf = b"not a pdf".to_vec();
+        let e = merge_pdfs(&entries).unwrap_err();
+        assert!(e.to_string().contains("merging pdf for '2'"), "{e}");
+    }
+
+    #[test]
+    fn test_zip_pdfs() {
+        let entries = entries();
+        let data = bundle(&entries, BundleFormat::Zip).unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(data)).unwrap();
+        assert_eq!(archive.len(), 2);
+        for entry in &entries {
+            let mut file = archive.by_name(&entry.name).unwrap();
+            let mut pdf = Vec::new();
+            file.read_to_end(&mut pdf).unwrap();
+            assert_eq!(pdf, entry.pdf);
+        }
+
+        let duplicate = vec![entries[0].clone(), entries[0].clone()];
+        assert!(zip_pdfs(&duplicate).is_err());
+    }
+}
@@ -0,0 +1,333 @@
+//! Look up bill-to and ship-to parties by account id.
+//!
+//! Upstream systems often know only the customer's account id. A [`CustomerDirectory`] holds the
+//! full [`Party`] of every known customer, read from a local JSON or CSV file, and replaces
+//! account id references in an invoice with those parties before it is deserialized:
+//!
+//! - `"bill_to": "C-42"` or `"ship_to": "C-42"` is replaced by the party of account `C-42`
+//! - an invoice without `bill_to` is billed to the customer of its own `acct_id`
+//!
+//! A `bill_to` reference also fills in a missing `acct_id`, so it still shows on the invoice.
+//!
+//! # File formats
+//! JSON files hold an array of parties, each with an extra `acct_id` key:
+//!
+//! ```json
+//! [{"acct_id": "C-42", "name": "Alice's Shop", "email": "alice@example.com"}]
+//! ```
+//!
+//! CSV files have a header row naming the columns `acct_id` and `name` (required), `phone`,
+//! `email`, and the address columns `line1`, `line2`, `city`, `province_code` and
+//! `postal_code`. Leaving every address column empty means the customer has no address.
+
+use std::{
+    collections::{BTreeMap, btree_map::Entry},
+    fs::File,
+    io::{BufReader, Read},
+    path::Path,
+};
+
+use csv::{ReaderBuilder, Trim};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{Address, AddressBuilder, Party, PartyBuilder, error::AddContext};
+
+/// A customer record in the JSON layout
+#[derive(Deserialize)]
+struct JsonCustomer {
+    acct_id: String,
+    #[serde(flatten)]
+    party: Party,
+}
+
+/// A customer record in the CSV layout
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CsvCustomer {
+    acct_id: String,
+    name: String,
+    phone: Option<String>,
+    email: Option<String>,
+    line1: Option<String>,
+    line2: Option<String>,
+    city: Option<String>,
+    province_code: Option<String>,
+    postal_code: Option<String>,
+}
+
+impl CsvCustomer {
+    fn address(&self) -> Result<Option<Address>, crate::Error> {
+        if [
+            &self.line1,
+            &self.line2,
+            &self.city,
+            &self.province_code,
+            &self.postal_code,
+        ]
+        .iter()
+        .all(|column| column.is_none())
+        {
+            return Ok(None);
+        }
+        let required = |column: &str, value: &Option<String>| {
+            value.clone().ok_or_else(|| {
+                crate::Error::invalid(format!(
+                    "column '{column}': missing value for a partial address"
+                ))
+            })
+        };
+        let mut builder = AddressBuilder::default()
+            .line1(required("line1", &self.line1)?)
+            .city(required("city", &self.city)?)
+            .province_code(required("province_code", &self.province_code)?)
+            .postal_code(required("postal_code", &self.postal_code)?);
+        if let Some(line2) = &self.line2 {
+            builder = builder.line2(line2);
+        }
+        builder.build().map(Some).map_err(crate::Error::from)
+    }
+
+    fn party(&self) -> Result<Party, crate::Error> {
+        let mut builder = PartyBuilder::default().name(&self.name);
+        if let Some(phone) = &self.phone {
+            builder = builder.phone(phone);
+        }
+        if let Some(email) = &self.email {
+            builder = builder.email(email);
+        }
+        if let Some(address) = self.address()? {
+            builder = builder.address(address);
+        }
+        builder.build().map_err(crate::Error::from)
+    }
+}
+
+/// Known customers keyed by account id
+#[derive(Debug, Clone, Default)]
+pub struct CustomerDirectory {
+    customers: BTreeMap<String, Party>,
+}
+
+impl CustomerDirectory {
+    /// Add a customer
+    ///
+    /// # Errors
+    /// - [`crate::Error`] if a customer with the same account id was already added
+    pub fn insert(&mut self, acct_id: &str, party: Party) -> Result<(), crate::Error> {
+        match self.customers.entry(acct_id.to_string()) {
+            Entry::Vacant(e) => {
+                e.insert(party);
+                Ok(())
+            }
+            Entry::Occupied(_) => Err(crate::Error::invalid(format!(
+                "customer '{acct_id}' is listed more than once"
+            ))),
+        }
+    }
+
+    /// Number of known customers
+    pub fn len(&self) -> usize {
+        self.customers.len()
+    }
+
+    /// Whether no customers are known
+    pub fn is_empty(&self) -> bool {
+        self.customers.is_empty()
+    }
+
+    /// Look up the party of a customer
+    ///
+    /// # Errors
+    /// - [`crate::Error`] if no customer has the account id
+    ///
+    /// # Example
+    /// ```rust
+    /// use invoice_pdf::{PartyBuilder, customers::CustomerDirectory};
+    ///
+    /// let mut customers = CustomerDirectory::default();
+    /// customers
+    ///     .insert("C-42", PartyBuilder::default().name("Alice").build().unwrap())
+    ///     .unwrap();
+    /// assert_eq!(customers.get("C-42").unwrap().name(), "Alice");
+    /// assert!(customers.get("C-43").is_err());
+    /// ```
+    pub fn get(&self, acct_id: &str) -> Result<&Party, crate::Error> {
+        self.customers.get(acct_id).ok_or_else(|| {
+            crate::Error::invalid(if self.is_empty() {
+                format!("unknown customer '{acct_id}', no customer directory is loaded")
+            } else {
+                format!("unknown customer '{acct_id}' is not in the customer directory")
+            })
+        })
+    }
+
+    /// Read customers from a JSON array of parties with an `acct_id` key each
+    ///
+    /// # Errors
+    /// - [`crate::Error`] if the JSON does not match the layout or an account id repeats
+    pub fn from_json<R: Read>(reader: R) -> Result<Self, crate::Error> {
+        let records: Vec<JsonCustomer> = serde_json::from_reader(reader)
+            .map_err(crate::Error::from)
+            .add_context("parsing customer JSON")?;
+        let mut directory = Self::default();
+        for (i, record) in records.into_iter().enumerate() {
+            directory
+                .insert(&record.acct_id, record.party)
+                .add_context(&format!("reading customer {i}"))?;
+        }
+        Ok(directory)
+    }
+
+    /// Read customers from a CSV file with one customer per row
+    ///
+    /// # Errors
+    /// - [`crate::Error`] if the CSV is malformed, a column is unknown, a required value or part
+    ///   of an address is missing, or an account id repeats. The message names the record at fault
+    pub fn from_csv<R: Read>(reader: R) -> Result<Self, crate::Error> {
+        let mut reader = ReaderBuilder::new().trim(Trim::All).from_reader(reader);
+        let mut directory = Self::default();
+        for record in reader.deserialize::<CsvCustomer>() {
+            let record = record
+                .map_err(crate::Error::from)
+                .add_context("reading customer csv record")?;
+            let row_context = format!("reading customer '{}'", record.acct_id);
+            let party = record.party().add_context(&row_context)?;
+            directory
+                .insert(&record.acct_id, party)
+                .add_context(&row_context)?;
+        }
+        Ok(directory)
+    }
+
+    /// Read customers from a file, as CSV when its extension is `.csv` and as JSON otherwise
+    ///
+    /// # Errors
+    /// - [`crate::Error`] if the file cannot be opened or read as described for
+    ///   [`Self::from_csv`] and [`Self::from_json`]
+    pub fn read_file(path: &Path) -> Result<Self, crate::Error> {
+        let context = format!("reading customers from '{}'", path.to_string_lossy());
+        let file = File::open(path)
+            .map_err(crate::Error::from)
+            .add_context(&context)?;
+        let reader = BufReader::new(file);
+        if path.extension().is_some_and(|ext| ext == "csv") {
+            Self::from_csv(reader)
+        } else {
+            Self::from_json(reader)
+        }
+        .add_context(&context)
+    }
+
+    /// Replace the customer references of an invoice in the JSON layout with full parties
+    ///
+    /// Values that are not JSON objects are left untouched.
+    ///
+    /// # Errors
+    /// - [`crate::Error`] if a referenced customer is unknown
+    pub fn resolve(&self, invoice: &mut Value) -> Result<(), crate::Error> {
+        let Value::Object(invoice) = invoice else {
+            return Ok(());
+        };
+
+        for key in ["bill_to", "ship_to"] {
+            let Some(Value::String(acct_id)) = invoice.get(key) else {
+                continue;
+            };
+            let acct_id = acct_id.clone();
+            let party = self
+                .get(&acct_id)
+                .add_context(&format!("resolving {key}"))?;
+            invoice.insert(
+                key.to_string(),
+                serde_json::to_value(party).map_err(crate::Error::from)?,
+            );
+            if key == "bill_to" && matches!(invoice.get("acct_id"), None | Some(Value::Null)) {
+                invoice.insert(String::from("acct_id"), Value::String(acct_id));
+            }
+        }
+
+        if !self.is_empty()
+            && matches!(invoice.get("bill_to"), None | Some(Value::Null))
+            && let Some(Value::String(acct_id)) = invoice.get("acct_id")
+        {
+            let party = self
+                .get(acct_id)
+                .add_context("resolving bill_to from acct_id")?;
+            let party = serde_json::to_value(party).map_err(crate::Error::from)?;
+            invoice.insert(String::from("bill_to"), party);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn directory() -> CustomerDirectory {
+        CustomerDirectory::from_csv(
+            "acct_id,name,email,line1,city,province_code,postal_code\n\
+             C-1,Alice's Shop,alice@example.com,1 Main St,Town,PA,12345\n\
+             C-2,Bob,,,,,\n"
+                .as_bytes(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_read_csv_and_json() {
+        let directory = directory();
+        assert_eq!(directory.len(), 2);
+        let alice = directory.get("C-1").unwrap();
+        assert_eq!(alice.email().as_deref(), Some("alice@example.com"));
+        assert_eq!(alice.address().as_ref().unwrap().city(), "Town");
+        assert!(directory.get("C-2").unwrap().address().is_none());
+
+        let e = CustomerDirectory::from_csv("acct_id,name,city\nC-1,Alice,Town\n".as_bytes())
+            .unwrap_err();
+        assert!(e.to_string().contains("'line1'"), "{e}");
+        let e = CustomerDirectory::from_csv("acct_id,name\nC-1,Alice\nC-1,Bob\n".as_bytes())
+            .unwrap_err();
+        assert!(e.to_string().contains("more than once"), "{e}");
+
+        let directory = CustomerDirectory::from_json(
+            r#"[{"acct_id": "C-3", "name": "Carol", "phone": "555-0100"}]"#.as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            directory.get("C-3").unwrap().phone().as_deref(),
+            Some("555-0100")
+        );
+    }
+
+    #[test]
+    fn test_resolve_references() {
+        let directory = directory();
+
+        let mut invoice = json!({"bill_to": "C-1", "ship_to": "C-2"});
+        directory.resolve(&mut invoice).unwrap();
+        assert_eq!(invoice["bill_to"]["name"], "Alice's Shop");
+        assert_eq!(invoice["ship_to"]["name"], "Bob");
+        assert_eq!(invoice["acct_id"], "C-1");
+
+        let mut invoice = json!({"acct_id": "C-2"});
+        directory.resolve(&mut invoice).unwrap();
+        assert_eq!(invoice["bill_to"]["name"], "Bob");
+
+        let mut invoice = json!({"acct_id": "C-2", "bill_to": {"name": "Own"}});
+        directory.resolve(&mut invoice).unwrap();
+        assert_eq!(invoice["bill_to"]["name"], "Own");
+
+        let e = directory
+            .resolve(&mut json!({"ship_to": "C-9"}))
+            .unwrap_err();
+        assert!(e.to_string().contains("unknown customer 'C-9'"), "{e}");
+        let e = CustomerDirectory::default()
+            .resolve(&mut json!({"bill_to": "C-1"}))
+            .unwrap_err();
+        assert!(e.to_string().contains("no customer directory"), "{e}");
+    }
+}
@@ -0,0 +1,373 @@
+//! Precision of invoice decimals and checks on the decimals of invoices read from the JSON layout.
+//!
+//! Every decimal of an [`crate::Invoice`] is rounded to the places its field keeps, as set by the
+//! invoice's [`RoundingPolicy`]. By default that is two places for quantities, line totals and
+//! amounts paid, and three for unit prices. Decimals given as JSON numbers with a
+//! fraction are read through a 64 bit float first, so `0.1` may not arrive as exactly one tenth.
+//! Both happen silently. [`check_decimals`] finds them before deserializing, either rejecting
+//! the invoice in [`DecimalMode::Strict`] mode or listing each change as an [`Adjustment`] in
+//! [`DecimalMode::Lenient`] mode.
+//!
+//! ```rust
+//! use invoice_pdf::decimal::{DecimalMode, check_decimals};
+//!
+//! let invoice = serde_json::json!({
+//!     "line_items": [{"quantity": "1", "price": "12.1291"}],
+//!     "paid": "0",
+//! });
+//! let adjustments = check_decimals(&invoice, DecimalMode::Lenient).unwrap();
+//! assert_eq!(adjustments[0].to_string(), "line_items[0].price: 12.1291 rounded to 12.130");
+//! assert!(check_decimals(&invoice, DecimalMode::Strict).is_err());
+//! ```
+
+use std::{fmt::Display, str::FromStr};
+
+use bigdecimal::BigDecimal;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::invoice::decimal_from_value;
+
+/// How a decimal is rounded to fewer places, mirroring [`bigdecimal::RoundingMode`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoundingMode {
+    /// Away from zero
+    Up,
+    /// Towards zero
+    Down,
+    /// Towards positive infinity
+    Ceiling,
+    /// Towards negative infinity
+    Floor,
+    /// To the nearest neighbor, with halves away from zero
+    HalfUp,
+    /// To the nearest neighbor, with halves towards zero
+    HalfDown,
+    /// To the nearest neighbor, with halves to the even neighbor
+    HalfEven,
+}
+
+impl From<RoundingMode> for bigdecimal::RoundingMode {
+    fn from(value: RoundingMode) -> Self {
+        match value {
+            RoundingMode::Up => bigdecimal::RoundingMode::Up,
+            RoundingMode::Down => bigdecimal::RoundingMode::Down,
+            RoundingMode::Ceiling => bigdecimal::RoundingMode::Ceiling,
+            RoundingMode::Floor => bigdecimal::RoundingMode::Floor,
+            RoundingMode::HalfUp => bigdecimal::RoundingMode::HalfUp,
+            RoundingMode::HalfDown => bigdecimal::RoundingMode::HalfDown,
+            RoundingMode::HalfEven => bigdecimal::RoundingMode::HalfEven,
+        }
+    }
+}
+
+/// The decimal places a field keeps and how values with more are rounded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Precision {
+    pub places: u32,
+    pub mode: RoundingMode,
+}
+
+impl Precision {
+    pub const fn new(places: u32, mode: RoundingMode) -> Self {
+        Self { places, mode }
+    }
+
+    /// Round `value` to the places kept
+    pub fn round(&self, value: &BigDecimal) -> BigDecimal {
+        value.with_scale_round(i64::from(self.places), self.mode.into())
+    }
+
+    /// Round `value` and print it with exactly the places kept, such as `6.00` for 2 places
+    pub fn format(&self, value: &BigDecimal) -> String {
+        let places = self.places as usize;
+        format!("{:.places$}", self.round(value))
+    }
+}
+
+/// Where the invoice total is rounded
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TotalRounding {
+    /// Round each line total, then add them up
+    #[default]
+    PerLine,
+    /// Add up the unrounded line extensions, then round the sum once
+    PerInvoice,
+}
+
+/// The precision of each kind of decimal on an invoice
+///
+/// Deserializes from the `rounding` object of an invoice, where any precision left out keeps its
+/// default:
+///
+/// ```json
+/// {"price": {"places": 4, "mode": "half_up"}, "quantity": {"places": 3, "mode": "half_up"}}
+/// ```
+///
+/// `"totals": "per_invoice"` rounds the invoice total once instead of line by line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RoundingPolicy {
+    /// Unit prices of line items, by default rounded up to 3 places
+    pub price: Precision,
+    /// Quantities of line items, by default rounded half-even to 2 places
+    pub quantity: Precision,
+    /// Line totals, the invoice total and amounts paid, by default rounded half-even to 2 places
+    pub total: Precision,
+    /// Whether the invoice total is rounded line by line or once
+    pub totals: TotalRounding,
+}
+
+impl Default for RoundingPolicy {
+    fn default() -> Self {
+        Self {
+            price: Precision::new(3, RoundingMode::Up),
+            quantity: Precision::new(2, RoundingMode::HalfEven),
+            total: Precision::new(2, RoundingMode::HalfEven),
+            totals: TotalRounding::PerLine,
+        }
+    }
+}
+
+impl RoundingPolicy {
+    /// Whether this is the default policy
+    pub fn is_default(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// How decimals that do not fit their field are handled when reading an invoice
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DecimalMode {
+    /// Round them and record an [`Adjustment`] for each one
+    #[default]
+    Lenient,
+    /// Reject the invoice
+    Strict,
+}
+
+/// A decimal that was changed to fit its field
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Adjustment {
+    /// Path of the field, such as `line_items[3].price`
+    pub path: String,
+    /// The value as it was given
+    pub original: String,
+    /// The value the invoice holds
+    pub rounded: String,
+}
+
+impl Display for Adjustment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} rounded to {}",
+            self.path, self.original, self.rounded
+        )
+    }
+}
+
+/// Collects what was found about the decimals of one invoice
+struct Checker {
+    mode: DecimalMode,
+    rounding: RoundingPolicy,
+    adjustments: Vec<Adjustment>,
+    problems: Vec<String>,
+}
+
+impl Checker {
+    fn check(&mut self, value: Option<&Value>, path: String, precision: Precision) {
+        let Some(value) = value else {
+            return;
+        };
+        // Values that are not decimals at all are left for deserializing to report
+        let Ok(parsed) = decimal_from_value(value) else {
+            return;
+        };
+        let (original, written) = match value {
+            Value::String(s) => (s.clone(), parsed.clone()),
+            Value::Number(n) if n.is_f64() => {
+                if self.mode == DecimalMode::Strict {
+                    self.problems.push(format!(
+                        "{path}: {n} is a JSON number with a fraction, which is read through a 64 \
+                         bit float. Give it as the string \"{n}\" instead"
+                    ));
+                    return;
+                }
+                let written = BigDecimal::from_str(&n.to_string()).unwrap_or(parsed.clone());
+                (n.to_string(), written)
+            }
+            other => (other.to_string(), parsed.clone()),
+        };
+
+        let rounded = precision.round(&parsed);
+        if rounded == written {
+            return;
+        }
+        match self.mode {
+            DecimalMode::Strict => self.problems.push(format!(
+                "{path}: {original} has more than {} decimal places",
+                precision.places
+            )),
+            DecimalMode::Lenient => self.adjustments.push(Adjustment {
+                path,
+                original,
+                rounded: rounded.to_string(),
+            }),
+        }
+    }
+}
+
+/// Check the decimals of an invoice in the JSON layout against the places their fields keep
+///
+/// The places are those of the invoice's own `rounding`, or the defaults if it has none or it
+/// cannot be read.
+///
+/// # Returns
+/// - Every decimal that deserializing will round, in [`DecimalMode::Lenient`] mode. Always empty
+///   in [`DecimalMode::Strict`] mode
+///
+/// # Errors
+/// - [`crate::Error`] in [`DecimalMode::Strict`] mode listing every decimal with too many places
+///   and every JSON number with a fraction
+pub fn check_decimals(invoice: &Value, mode: DecimalMode) -> Result<Vec<Adjustment>, crate::Error> {
+    let rounding = invoice
+        .get("rounding")
+        .filter(|rounding| !rounding.is_null())
+        .and_then(|rounding| serde_json::from_value(rounding.clone()).ok())
+        .unwrap_or_default();
+    let mut checker = Checker {
+        mode,
+        rounding,
+        adjustments: Vec::new(),
+        problems: Vec::new(),
+    };
+    if let Some(Value::Array(lines)) = invoice.get("line_items") {
+        for (i, line) in lines.iter().enumerate() {
+            let quantity = checker.rounding.quantity;
+            checker.check(
+                line.get("quantity"),
+                format!("line_items[{i}].quantity"),
+                quantity,
+            );
+            let price = checker.rounding.price;
+            checker.check(line.get("price"), format!("line_items[{i}].price"), price);
+        }
+    }
+    let total = checker.rounding.total;
+    checker.check(invoice.get("paid"), String::from("paid"), total);
+    if let Some(Value::Array(payments)) = invoice.get("payments") {
+        for (i, payment) in payments.iter().enumerate() {
+            checker.check(
+                payment.get("amount"),
+                format!("payments[{i}].amount"),
+                total,
+            );
+        }
+    }
+
+    if checker.problems.is_empty() {
+        Ok(checker.adjustments)
+    } else {
+        Err(crate::Error::invalid(format!(
+            "decimals do not fit their fields exactly: {}",
+            checker.problems.join("; ")
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn invoice(quantity: Value, price: Value, paid: Value) -> Value {
+        json!({
+            "line_items": [
+                {"quantity": "1", "price": "1"},
+                {"quantity": quantity, "price": price},
+            ],
+            "paid": paid,
+        })
+    }
+
+    #[test]
+    fn test_exact_decimals() {
+        let strings = invoice(json!("2.50"), json!(3), json!("1.5"));
+        assert_eq!(check_decimals(&strings, DecimalMode::Strict).unwrap(), []);
+        assert_eq!(check_decimals(&strings, DecimalMode::Lenient).unwrap(), []);
+        let floats = invoice(json!(1.25), json!(0.5), json!(2));
+        assert_eq!(check_decimals(&floats, DecimalMode::Lenient).unwrap(), []);
+    }
+
+    #[test]
+    fn test_lenient_reports_adjustments() {
+        let mut invoice = invoice(json!("1.005"), json!(0.1), json!("5.001"));
+        invoice["payments"] = json!([{"amount": "2"}, {"amount": "2.345"}]);
+        let adjustments = check_decimals(&invoice, DecimalMode::Lenient).unwrap();
+        assert_eq!(
+            adjustments
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "line_items[1].quantity: 1.005 rounded to 1.00",
+                // The float nearest to 0.1 is slightly above it, so rounding up adds a thousandth
+                "line_items[1].price: 0.1 rounded to 0.101",
+                "paid: 5.001 rounded to 5.00",
+                "payments[1].amount: 2.345 rounded to 2.34",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strict_rejects() {
+        let invoice = invoice(json!("1.005"), json!(0.5), json!("5"));
+        let e = check_decimals(&invoice, DecimalMode::Strict).unwrap_err();
+        assert_eq!(e.kind.name(), "invalid");
+        let message = e.to_string();
+        assert!(
+            message.contains("line_items[1].quantity: 1.005 has more than 2 decimal places"),
+            "{message}"
+        );
+        assert!(
+            message.contains("line_items[1].price: 0.5 is a JSON number with a fraction"),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn test_invoice_rounding_policy() {
+        let mut invoice = invoice(json!("1.005"), json!("2.12345"), json!("5"));
+        invoice["rounding"] = json!({
+            "price": {"places": 4, "mode": "half_up"},
+            "quantity": {"places": 3, "mode": "half_up"},
+        });
+        let adjustments = check_decimals(&invoice, DecimalMode::Lenient).unwrap();
+        assert_eq!(
+            adjustments
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["line_items[1].price: 2.12345 rounded to 2.1235"]
+        );
+    }
+
+    #[test]
+    fn test_precision_round() {
+        let value = BigDecimal::from_str("-2.125").unwrap();
+        let round = |mode| Precision::new(2, mode).round(&value).to_string();
+        assert_eq!(round(RoundingMode::Up), "-2.13");
+        assert_eq!(round(RoundingMode::Down), "-2.12");
+        assert_eq!(round(RoundingMode::Ceiling), "-2.12");
+        assert_eq!(round(RoundingMode::Floor), "-2.13");
+        assert_eq!(round(RoundingMode::HalfUp), "-2.13");
+        assert_eq!(round(RoundingMode::HalfDown), "-2.12");
+        assert_eq!(round(RoundingMode::HalfEven), "-2.12");
+    }
+}
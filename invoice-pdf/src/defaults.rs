@@ -0,0 +1,463 @@
+//! Default values filled into invoices that leave them out.
+//!
+//! Callers that print many invoices for the same business usually repeat the sender, logo and
+//! post scripts in every invoice. [`InvoiceDefaults`] holds those values once and merges them
+//! under each invoice, so a value given by the invoice always wins over the default. Merging
+//! happens on the JSON layout before deserializing, which lets an invoice omit fields that are
+//! otherwise required, such as `sender` and `post_scripts`.
+//!
+//! Businesses invoicing from several entities can define each one as a named [`SenderProfile`].
+//! An invoice picks one with `"sender_profile": "<name>"`, and the profile's sender, logo, payment
+//! link and post scripts are used in place of the plain ones. Customer references in `bill_to`
+//! and `ship_to` are resolved through [`InvoiceDefaults::customers`] before either.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    Invoice, Party,
+    customers::CustomerDirectory,
+    decimal::{DecimalMode, RoundingPolicy, check_decimals},
+    error::AddContext,
+    terms::PaymentTerms,
+};
+
+/// Values used for any invoice field that the invoice itself leaves out
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InvoiceDefaults {
+    /// The party sending the invoices
+    pub sender: Option<Party>,
+    /// Path to the logo image
+    pub logo: Option<String>,
+    /// Prefix of the payment link. An invoice without a `payment_url` gets this followed by its id
+    pub payment_url_base: Option<String>,
+    /// Post scripts for invoices that do not list any
+    pub post_scripts: Vec<String>,
+    /// Payment terms for invoices without their own `terms`
+    pub terms: Option<PaymentTerms>,
+    /// Precision of decimals for invoices without their own `rounding`. Precisions an invoice's
+    /// `rounding` leaves out are taken from here as well
+    pub rounding: Option<RoundingPolicy>,
+    /// Sender profiles invoices can pick by name with `sender_profile`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, SenderProfile>,
+    /// Customers that `bill_to`, `ship_to` and `acct_id` refer to by account id. Loaded from a
+    /// customer file rather than deserialized with the other defaults
+    #[serde(skip)]
+    pub customers: CustomerDirectory,
+    /// Whether invoices with decimals that do not fit their fields are rounded or rejected. Set
+    /// by the caller rather than deserialized with the other defaults
+    #[serde(skip)]
+    pub decimals: DecimalMode,
+}
+
+/// One entity invoices can be sent from, picked by an invoice's `sender_profile`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SenderProfile {
+    /// The party sending the invoice
+    pub sender: Party,
+    /// Path to the entity's logo image
+    #[serde(default)]
+    pub logo: Option<String>,
+    /// Prefix of the entity's payment link, followed by the invoice id
+    #[serde(default)]
+    pub payment_url_base: Option<String>,
+    /// Post scripts for invoices that do not list any, such as remit-to instructions
+    #[serde(default)]
+    pub post_scripts: Vec<String>,
+}
+
+/// Fields filled in as a whole, so a party given by the invoice never picks up the phone or
+/// address of the default party
+const WHOLE_VALUES: [&str; 3] = ["sender", "bill_to", "ship_to"];
+
+/// Copy every value of `defaults` into `target` where `target` is missing it, descending into
+/// objects present in both other than [`WHOLE_VALUES`]. A value explicitly set to `null` is kept
+fn merge_under(target: &mut Map<String, Value>, defaults: &Map<String, Value>) {
+    for (key, default) in defaults {
+        match target.get_mut(key) {
+            None => {
+                target.insert(key.clone(), default.clone());
+            }
+            Some(Value::Object(existing)) if !WHOLE_VALUES.contains(&key.as_str()) => {
+                if let Value::Object(default) = default {
+                    merge_under(existing, default);
+                }
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+impl InvoiceDefaults {
+    /// Whether there is nothing to fill in and decimals are only rounded rather than rejected
+    pub fn is_empty(&self) -> bool {
+        self.decimals == DecimalMode::Lenient
+            && self.sender.is_none()
+            && self.logo.is_none()
+            && self.payment_url_base.is_none()
+            && self.post_scripts.is_empty()
+            && self.terms.is_none()
+            && self.rounding.is_none()
+            && self.profiles.is_empty()
+            && self.customers.is_empty()
+    }
+
+    /// Look up the sender profile named by an invoice
+    ///
+    /// # Errors
+    /// - [`crate::Error`] if no profile has that name
+    pub fn profile(&self, name: &str) -> Result<&SenderProfile, crate::Error> {
+        self.profiles.get(name).ok_or_else(|| {
+            let known = self.profiles.keys().cloned().collect::<Vec<_>>();
+            crate::Error::invalid(if known.is_empty() {
+                format!("unknown sender profile '{name}', no profiles are defined")
+            } else {
+                format!(
+                    "unknown sender profile '{name}', expected one of {}",
+                    known.join(", ")
+                )
+            })
+        })
+    }
+
+    /// Merge the defaults under an invoice in the JSON layout
+    ///
+    /// Customer references are resolved first. Then the invoice's `sender_profile` key is removed
+    /// and the named profile's values used in place of the plain sender defaults. Values that are
+    /// not JSON objects are left untouched, so deserializing them reports the same error it would
+    /// have without defaults.
+    ///
+    /// # Errors
+    /// - [`crate::Error`] if `sender_profile` is not a string or names an unknown profile
+    /// - [`crate::Error`] if the invoice refers to an unknown customer
+    pub fn apply(&self, invoice: &mut Value) -> Result<(), crate::Error> {
+        self.customers.resolve(invoice)?;
+        let Value::Object(invoice) = invoice else {
+            return Ok(());
+        };
+
+        let profile = match invoice.remove("sender_profile") {
+            None | Some(Value::Null) => None,
+            Some(Value::String(name)) => Some(self.profile(&name)?),
+            Some(other) => {
+                return Err(crate::Error::invalid(format!(
+                    "sender_profile must be a string, found {other}"
+                )));
+            }
+        };
+        self.fill(invoice, profile);
+        Ok(())
+    }
+
+    /// Merge the defaults under an invoice object, taking the sender, logo, payment link and post
+    /// scripts from `profile` instead of the plain defaults when one is given
+    fn fill(&self, invoice: &mut Map<String, Value>, profile: Option<&SenderProfile>) {
+        let (sender, logo, payment_url_base, post_scripts) = match profile {
+            Some(profile) => (
+                Some(&profile.sender),
+                &profile.logo,
+                &profile.payment_url_base,
+                &profile.post_scripts,
+            ),
+            None => (
+                self.sender.as_ref(),
+                &self.logo,
+                &self.payment_url_base,
+                &self.post_scripts,
+            ),
+        };
+        let mut defaults = Map::new();
+        if let Some(sender) = sender {
+            defaults.insert(
+                String::from("sender"),
+                // A party always serializes to an object
+                serde_json::to_value(sender).unwrap_or(Value::Null),
+            );
+        }
+        if let Some(logo) = logo {
+            defaults.insert(String::from("logo"), Value::String(logo.clone()));
+        }
+        if let (Some(base), Some(Value::String(id))) = (payment_url_base, invoice.get("id")) {
+            defaults.insert(
+                String::from("payment_url"),
+                Value::String(format!("{base}{id}")),
+            );
+        }
+        if !post_scripts.is_empty() {
+            defaults.insert(
+                String::from("post_scripts"),
+                Value::from(post_scripts.clone()),
+            );
+        }
+        if let Some(terms) = &self.terms {
+            defaults.insert(String::from("terms"), Value::String(terms.to_string()));
+        }
+        if let Some(rounding) = &self.rounding {
+            defaults.insert(
+                String::from("rounding"),
+                // A rounding policy always serializes to an object
+                serde_json::to_value(rounding).unwrap_or(Value::Null),
+            );
+        }
+        merge_under(invoice, &defaults);
+    }
+
+    /// Merge the defaults under an invoice in the JSON layout and deserialize it
+    ///
+    /// Decimals are checked with [`check_decimals`] in the mode set by
+    /// [`InvoiceDefaults::decimals`], and any rounding is kept in
+    /// [`Invoice::rounding_adjustments`].
+    ///
+    /// # Errors
+    /// - [`crate::Error`] if the merged value does not match the invoice layout
+    /// - [`crate::Error`] if the invoice names an unknown sender profile or customer
+    /// - [`crate::Error`] in strict mode if a decimal has more places than its field keeps
+    pub fn invoice_from_value(&self, mut value: Value) -> Result<Invoice, crate::Error> {
+        self.apply(&mut value)?;
+        let adjustments =
+            check_decimals(&value, self.decimals).add_context("checking invoice decimals")?;
+        serde_json::from_value::<Invoice>(value)
+            .map(|invoice| invoice.with_rounding_adjustments(adjustments))
+            .map_err(crate::Error::from)
+            .add_context("deserializing invoice")
+    }
+
+    /// Merge the defaults under an already parsed invoice
+    ///
+    /// Used for formats that build invoices directly rather than through the JSON layout, such as
+    /// the CSV and ABC readers. Those keep their decimals as given, so they are checked the same
+    /// way as in [`Self::invoice_from_value`] even when there is nothing to fill in.
+    pub fn apply_to_invoice(&self, invoice: Invoice) -> Result<Invoice, crate::Error> {
+        let mut value = serde_json::to_value(&invoice)
+            .map_err(crate::Error::from)
+            .add_context(&format!("serializing invoice {}", invoice.id()))?;
+        // A serialized invoice writes its unset fields as `null`, which would otherwise be kept
+        if let Value::Object(fields) = &mut value {
+            fields.retain(|_, field| !field.is_null());
+        }
+        self.invoice_from_value(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::decimal::{Precision, RoundingMode};
+    use crate::{AddressBuilder, PartyBuilder};
+
+    fn defaults() -> InvoiceDefaults {
+        InvoiceDefaults {
+            sender: Some(
+                PartyBuilder::default()
+                    .name("Default Sender")
+                    .phone("555-0100")
+                    .address(
+                        AddressBuilder::default()
+                            .line1("1 Main St")
+                            .city("Town")
+                            .province_code("PA")
+                            .postal_code("12345")
+                            .build()
+                            .unwrap(),
+                    )
+                    .build()
+                    .unwrap(),
+            ),
+            logo: Some(String::from("./logo.png")),
+            payment_url_base: Some(String::from("https://pay.example.com/")),
+            post_scripts: vec![String::from("Thank you")],
+            ..InvoiceDefaults::default()
+        }
+    }
+
+    fn invoice() -> Value {
+        json!({
+            "id": "7",
+            "created_datetime": "2026-02-10T12:00:00+00:00",
+            "net_due_datetime": "2026-03-10T12:00:00+00:00",
+            "bill_to": {"name": "B"},
+            "line_items": [],
+            "paid": "0"
+        })
+    }
+
+    #[test]
+    fn test_fills_missing_fields() {
+        let invoice = defaults().invoice_from_value(invoice()).unwrap();
+        assert_eq!(invoice.sender().name(), "Default Sender");
+        assert_eq!(invoice.logo().as_deref(), Some(Path::new("./logo.png")));
+        assert_eq!(
+            invoice.payment_url().as_deref(),
+            Some("https://pay.example.com/7")
+        );
+        assert_eq!(invoice.post_scripts(), &vec![String::from("Thank you")]);
+    }
+
+    #[test]
+    fn test_invoice_values_win() {
+        let mut value = invoice();
+        value["sender"] = json!({"name": "Own Sender", "phone": null});
+        value["logo"] = json!(null);
+        value["payment_url"] = json!("https://own.example.com");
+        value["post_scripts"] = json!([]);
+        let invoice = defaults().invoice_from_value(value).unwrap();
+
+        assert_eq!(invoice.sender().name(), "Own Sender");
+        // The invoice's sender replaces the default sender rather than being merged with it
+        assert_eq!(invoice.sender().phone().as_deref(), None);
+        assert!(invoice.sender().address().is_none());
+        assert!(invoice.logo().is_none());
+        assert_eq!(
+            invoice.payment_url().as_deref(),
+            Some("https://own.example.com")
+        );
+        assert!(invoice.post_scripts().is_empty());
+    }
+
+    #[test]
+    fn test_empty_defaults_change_nothing() {
+        let e = InvoiceDefaults::default()
+            .invoice_from_value(invoice())
+            .unwrap_err();
+        assert!(format!("{e:?}").contains("missing field"), "{e:?}");
+    }
+
+    #[test]
+    fn test_sender_profile() {
+        let mut defaults = defaults();
+        defaults.profiles.insert(
+            String::from("east"),
+            SenderProfile {
+                sender: PartyBuilder::default().name("East LLC").build().unwrap(),
+                logo: Some(String::from("./east.png")),
+                payment_url_base: None,
+                post_scripts: vec![String::from("Remit to East LLC")],
+            },
+        );
+
+        let mut value = invoice();
+        value["sender_profile"] = json!("east");
+        let invoice = defaults.invoice_from_value(value).unwrap();
+        assert_eq!(invoice.sender().name(), "East LLC");
+        assert_eq!(invoice.sender().phone().as_deref(), None);
+        assert_eq!(invoice.logo().as_deref(), Some(Path::new("./east.png")));
+        // The profile has no payment link, so the default one is not used either
+        assert!(invoice.payment_url().is_none());
+        assert_eq!(
+            invoice.post_scripts(),
+            &vec![String::from("Remit to East LLC")]
+        );
+
+        let mut value = self::invoice();
+        value["sender_profile"] = json!("west");
+        let e = defaults.invoice_from_value(value).unwrap_err();
+        assert!(e.to_string().contains("expected one of east"), "{e}");
+    }
+
+    #[test]
+    fn test_decimal_mode() {
+        let mut value = invoice();
+        value["paid"] = json!("1.999");
+        let invoice = defaults().invoice_from_value(value.clone()).unwrap();
+        assert_eq!(invoice.paid().to_string(), "2.00");
+        assert_eq!(
+            invoice.rounding_adjustments()[0].to_string(),
+            "paid: 1.999 rounded to 2.00"
+        );
+        let merged = defaults().apply_to_invoice(invoice).unwrap();
+        assert_eq!(merged.rounding_adjustments().len(), 1);
+
+        let strict = InvoiceDefaults {
+            decimals: DecimalMode::Strict,
+            ..defaults()
+        };
+        let e = strict.invoice_from_value(value).unwrap_err();
+        assert!(e.to_string().contains("paid: 1.999 has more than 2"), "{e}");
+    }
+
+    #[test]
+    fn test_imported_decimals() {
+        let data = "invoice_id,sender_name,bill_to_name,sku,title,quantity,price\n\
+                    1,Sender,Customer,A1,Widget,2,12.1291\n";
+        let imported = crate::import::csv::read_invoices(data.as_bytes())
+            .unwrap()
+            .remove(0);
+
+        let invoice = InvoiceDefaults::default()
+            .apply_to_invoice(imported.clone())
+            .unwrap();
+        assert_eq!(invoice.line_items()[0].price().to_string(), "12.130");
+        assert_eq!(
+            invoice.rounding_adjustments()[0].to_string(),
+            "line_items[0].price: 12.1291 rounded to 12.130"
+        );
+
+        let strict = InvoiceDefaults {
+            decimals: DecimalMode::Strict,
+            ..InvoiceDefaults::default()
+        };
+        assert!(!strict.is_empty());
+        let e = strict.apply_to_invoice(imported).unwrap_err();
+        assert!(
+            e.to_string()
+                .contains("line_items[0].price: 12.1291 has more than 3"),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn test_default_terms() {
+        let defaults = InvoiceDefaults {
+            terms: Some("Net 15".parse().unwrap()),
+            ..defaults()
+        };
+        let mut value = invoice();
+        value.as_object_mut().unwrap().remove("net_due_datetime");
+        let invoice = defaults.invoice_from_value(value).unwrap();
+        assert_eq!(invoice.terms().as_ref().unwrap().to_string(), "Net 15");
+        assert_eq!(
+            invoice.net_due_datetime().to_rfc3339(),
+            "2026-02-25T12:00:00+00:00"
+        );
+
+        // An invoice's own due date wins over the date the default terms give
+        let invoice = defaults.invoice_from_value(self::invoice()).unwrap();
+        assert_eq!(
+            invoice.net_due_datetime().to_rfc3339(),
+            "2026-03-10T12:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_default_rounding() {
+        let defaults = InvoiceDefaults {
+            rounding: Some(RoundingPolicy {
+                price: Precision::new(4, RoundingMode::HalfUp),
+                ..RoundingPolicy::default()
+            }),
+            ..defaults()
+        };
+        let mut value = invoice();
+        value["line_items"] =
+            json!([{"sku": "A", "title": "T", "quantity": "1", "price": "0.12345"}]);
+        value["rounding"] = json!({"quantity": {"places": 3, "mode": "down"}});
+        let invoice = defaults.invoice_from_value(value).unwrap();
+        assert_eq!(invoice.rounding().price.places, 4);
+        assert_eq!(invoice.rounding().quantity.mode, RoundingMode::Down);
+        assert_eq!(invoice.line_items()[0].price().to_string(), "0.1235");
+        assert!(
+            invoice.rounding_adjustments()[0]
+                .to_string()
+                .ends_with("rounded to 0.1235")
+        );
+    }
+}
@@ -5,32 +5,331 @@
 //! can be appended as errors propagate upward, allowing the final error
 //! message presented to the user to contain useful, ordered diagnostic
 //! information.
+//!
+//! The context stack is kept apart from the root cause. The root cause is the
+//! [`ErrorKind`], whose message describes the failure without repeating any
+//! error it wraps. The wrapped error is returned by [`std::error::Error::source`],
+//! so logging frameworks walking the chain print each cause once and callers can
+//! downcast it.
+//!
+//! ```rust
+//! use invoice_pdf::error::AddContext;
+//!
+//! let e: Result<(), invoice_pdf::Error> = Err(invoice_pdf::Error::from(
+//!     std::io::Error::other("disk full"),
+//! ));
+//! let e = e.add_context("writing invoice 7").unwrap_err();
+//! assert_eq!(e.to_string(), "writing invoice 7 -> I/O error");
+//! assert_eq!(format!("{e:?}"), "writing invoice 7 -> I/O error: disk full");
+//! assert_eq!(
+//!     format!("{e:#}"),
+//!     "writing invoice 7\n\nCaused by:\n    0: I/O error\n    1: disk full"
+//! );
+//! ```
+
+use std::{
+    fmt::{Debug, Display},
+    path::PathBuf,
+};
 
-use std::fmt::{Debug, Display};
+use serde::Serialize;
+
+use crate::{
+    AddressBuilderError, InvoiceBuilderError, LineItemBuilderError, PartyBuilderError,
+    validation::Issue,
+};
 
 /// Generic error type carrying an inner kind and a stack of context messages.
 ///
 /// Context messages are intended to be added at each layer where the error is
 /// propagated so the final message is informative to the user or operator.
+///
+/// `{}` prints the context from the outermost layer in and then the root cause
+/// on one line, separated by `->`. `{:?}` prints the same line followed by the
+/// message of each wrapped error, for showing the whole error where only one
+/// line fits. The alternate form `{:#}` prints the outermost context followed by
+/// a numbered `Caused by:` list that continues through the wrapped errors.
 #[warn(dead_code)]
 pub struct Error {
     pub kind: ErrorKind,
     context: Vec<String>,
+    /// Boxed since most errors never learn these and `Error` is returned everywhere
+    details: Option<Box<Details>>,
+}
+
+/// What is known about the invoice and input an [`Error`] is about
+#[derive(Default)]
+struct Details {
+    invoice_id: Option<String>,
+    location: Location,
+}
+
+/// Where in the input an error was found. Every part is optional since not every input has
+/// lines or records
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Location {
+    /// The file the input came from, or `stdin`
+    pub source: Option<String>,
+    /// The 1-based line, or spreadsheet row, of the input
+    pub line: Option<usize>,
+    /// The 1-based column within the line
+    pub column: Option<usize>,
+    /// The 0-based position of the invoice in a batch
+    pub index: Option<usize>,
+}
+
+impl Location {
+    /// Whether nothing is known about the location
+    pub fn is_empty(&self) -> bool {
+        self == &Location::default()
+    }
+}
+
+/// Broad groups of [`ErrorKind`] for deciding how to react to an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ErrorCategory {
+    /// The invoice data is malformed or refers to something that does not exist
+    Input,
+    /// The invoice could not be turned into HTML, a QR code or a bundle
+    Rendering,
+    /// The browser used for printing could not be started or talked to
+    Browser,
+    /// Reading or writing files or streams failed
+    Io,
+    /// Anything else
+    Other,
 }
 
 /// Enumeration of concrete error kinds wrapped by [`Error`].
 ///
 /// This enum stores the original error values for potential programmatic use
 /// while `Error` itself keeps a human-readable context stack for display.
+/// [`ErrorKind::is_transient`] tells apart failures worth retrying from bad input.
 pub enum ErrorKind {
     Io(std::io::Error),
     FantocciniNewSession(fantoccini::error::NewSessionError),
     FantocciniCmdError(fantoccini::error::CmdError),
     FantocciniPrintError(fantoccini::error::PrintConfigurationError),
     Askama(askama::Error),
+    Csv(csv::Error),
+    /// Malformed JSON or JSON that does not match the expected layout. The wrapped error gives
+    /// the line and column through [`serde_json::Error::line`] and [`serde_json::Error::column`]
+    Json(serde_json::Error),
+    InvoiceBuilder(InvoiceBuilderError),
+    PartyBuilder(PartyBuilderError),
+    AddressBuilder(AddressBuilderError),
+    LineItemBuilder(LineItemBuilderError),
+    /// The invoice's logo could not be read
+    InvalidLogo {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The payment link could not be turned into a QR code image
+    QrCode(Box<dyn std::error::Error + Send + Sync>),
+    /// The chromedriver process could not be started
+    ChromedriverLaunch(std::io::Error),
+    /// The chromedriver process exited before it was ready
+    ChromedriverExited,
+    /// The chromedriver process started but was not listening on the port in time
+    ChromedriverNotListening(u16),
+    /// The port chromedriver listens on is taken by another process
+    PortInUse(u16),
+    /// Well formed input with values that cannot be used, such as an unknown customer
+    Invalid(String),
+    /// The invoice breaks business rules checked with [`crate::Invoice::validate`]. Holds only
+    /// the issues with error severity
+    Validation(Vec<Issue>),
+    #[cfg(feature = "yaml")]
+    Yaml(serde_yaml::Error),
+    #[cfg(feature = "toml")]
+    Toml(toml::de::Error),
+    #[cfg(feature = "bundle")]
+    Pdf(lopdf::Error),
+    #[cfg(feature = "bundle")]
+    Zip(zip::result::ZipError),
     Other(String),
 }
 
+/// The message of the kind itself. Wrapped errors are left to [`std::error::Error::source`]
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ErrorKind::Io(_) => write!(f, "I/O error"),
+            ErrorKind::FantocciniNewSession(_) => write!(f, "cannot start a browser session"),
+            ErrorKind::FantocciniCmdError(_) => write!(f, "browser command failed"),
+            ErrorKind::FantocciniPrintError(_) => write!(f, "invalid print configuration"),
+            ErrorKind::Askama(_) => write!(f, "cannot render template"),
+            ErrorKind::Csv(_) => write!(f, "invalid CSV"),
+            ErrorKind::Json(_) => write!(f, "invalid JSON"),
+            ErrorKind::InvoiceBuilder(_) => write!(f, "cannot build invoice"),
+            ErrorKind::PartyBuilder(_) => write!(f, "cannot build party"),
+            ErrorKind::AddressBuilder(_) => write!(f, "cannot build address"),
+            ErrorKind::LineItemBuilder(_) => write!(f, "cannot build line item"),
+            ErrorKind::InvalidLogo { path, .. } => {
+                write!(f, "cannot read logo '{}'", path.to_string_lossy())
+            }
+            ErrorKind::QrCode(_) => write!(f, "cannot draw payment QR code"),
+            ErrorKind::ChromedriverLaunch(_) => write!(f, "cannot launch chromedriver"),
+            ErrorKind::ChromedriverExited => write!(f, "chromedriver stopped unexpectedly"),
+            ErrorKind::ChromedriverNotListening(port) => {
+                write!(f, "chromedriver failed to bind to port {port}")
+            }
+            ErrorKind::PortInUse(port) => write!(f, "Port {port} is already in use"),
+            ErrorKind::Invalid(message) => write!(f, "{message}"),
+            ErrorKind::Validation(issues) => {
+                write!(f, "invoice failed {} validation rule(s): ", issues.len())?;
+                for (i, issue) in issues.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{issue}")?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "yaml")]
+            ErrorKind::Yaml(_) => write!(f, "invalid YAML"),
+            #[cfg(feature = "toml")]
+            ErrorKind::Toml(_) => write!(f, "invalid TOML"),
+            #[cfg(feature = "bundle")]
+            ErrorKind::Pdf(_) => write!(f, "cannot merge PDFs"),
+            #[cfg(feature = "bundle")]
+            ErrorKind::Zip(_) => write!(f, "cannot write zip archive"),
+            ErrorKind::Other(message) => write!(f, "{message}"),
+        }
+    }
+}
+
+impl ErrorKind {
+    /// Whether the failure comes from the browser environment rather than the input, so trying
+    /// again later may succeed
+    ///
+    /// A chromedriver that exits or fails to bind its port while starting may start on another
+    /// try. One that cannot be launched at all, such as one missing from the path, is a setup
+    /// problem and not transient.
+    ///
+    /// # Example
+    /// ```rust
+    /// use invoice_pdf::error::ErrorKind;
+    ///
+    /// assert!(ErrorKind::PortInUse(4444).is_transient());
+    /// assert!(ErrorKind::ChromedriverExited.is_transient());
+    /// assert!(ErrorKind::ChromedriverNotListening(4444).is_transient());
+    /// assert!(!ErrorKind::Other(String::from("bad input")).is_transient());
+    /// let missing = std::io::Error::from(std::io::ErrorKind::NotFound);
+    /// assert!(!ErrorKind::ChromedriverLaunch(missing).is_transient());
+    /// ```
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            ErrorKind::FantocciniNewSession(_)
+                | ErrorKind::FantocciniCmdError(_)
+                | ErrorKind::PortInUse(_)
+                | ErrorKind::ChromedriverExited
+                | ErrorKind::ChromedriverNotListening(_)
+        )
+    }
+
+    /// A short, stable name for the kind, for machine readable output
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Io(_) => "io",
+            ErrorKind::FantocciniNewSession(_) => "browser_session",
+            ErrorKind::FantocciniCmdError(_) => "browser_command",
+            ErrorKind::FantocciniPrintError(_) => "print_configuration",
+            ErrorKind::Askama(_) => "template",
+            ErrorKind::Csv(_) => "csv",
+            ErrorKind::Json(_) => "json",
+            ErrorKind::InvoiceBuilder(_) => "invoice_builder",
+            ErrorKind::PartyBuilder(_) => "party_builder",
+            ErrorKind::AddressBuilder(_) => "address_builder",
+            ErrorKind::LineItemBuilder(_) => "line_item_builder",
+            ErrorKind::InvalidLogo { .. } => "invalid_logo",
+            ErrorKind::QrCode(_) => "qr_code",
+            ErrorKind::ChromedriverLaunch(_) => "chromedriver_launch",
+            ErrorKind::ChromedriverExited => "chromedriver_exited",
+            ErrorKind::ChromedriverNotListening(_) => "chromedriver_not_listening",
+            ErrorKind::PortInUse(_) => "port_in_use",
+            ErrorKind::Invalid(_) => "invalid",
+            ErrorKind::Validation(_) => "validation",
+            #[cfg(feature = "yaml")]
+            ErrorKind::Yaml(_) => "yaml",
+            #[cfg(feature = "toml")]
+            ErrorKind::Toml(_) => "toml",
+            #[cfg(feature = "bundle")]
+            ErrorKind::Pdf(_) => "pdf",
+            #[cfg(feature = "bundle")]
+            ErrorKind::Zip(_) => "zip",
+            ErrorKind::Other(_) => "other",
+        }
+    }
+
+    /// The broad group the kind belongs to
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            ErrorKind::Csv(_)
+            | ErrorKind::Json(_)
+            | ErrorKind::InvoiceBuilder(_)
+            | ErrorKind::PartyBuilder(_)
+            | ErrorKind::AddressBuilder(_)
+            | ErrorKind::LineItemBuilder(_)
+            | ErrorKind::InvalidLogo { .. }
+            | ErrorKind::Invalid(_)
+            | ErrorKind::Validation(_) => ErrorCategory::Input,
+            #[cfg(feature = "yaml")]
+            ErrorKind::Yaml(_) => ErrorCategory::Input,
+            #[cfg(feature = "toml")]
+            ErrorKind::Toml(_) => ErrorCategory::Input,
+            ErrorKind::Askama(_) | ErrorKind::QrCode(_) => ErrorCategory::Rendering,
+            #[cfg(feature = "bundle")]
+            ErrorKind::Pdf(_) | ErrorKind::Zip(_) => ErrorCategory::Rendering,
+            ErrorKind::FantocciniNewSession(_)
+            | ErrorKind::FantocciniCmdError(_)
+            | ErrorKind::FantocciniPrintError(_)
+            | ErrorKind::ChromedriverLaunch(_)
+            | ErrorKind::ChromedriverExited
+            | ErrorKind::ChromedriverNotListening(_)
+            | ErrorKind::PortInUse(_) => ErrorCategory::Browser,
+            ErrorKind::Io(_) => ErrorCategory::Io,
+            ErrorKind::Other(_) => ErrorCategory::Other,
+        }
+    }
+
+    /// The error wrapped by this kind, if it wraps one
+    fn wrapped(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ErrorKind::Io(e) => Some(e),
+            ErrorKind::FantocciniNewSession(e) => Some(e),
+            ErrorKind::FantocciniCmdError(e) => Some(e),
+            ErrorKind::FantocciniPrintError(e) => Some(e),
+            ErrorKind::Askama(e) => Some(e),
+            ErrorKind::Csv(e) => Some(e),
+            ErrorKind::Json(e) => Some(e),
+            ErrorKind::InvoiceBuilder(e) => Some(e),
+            ErrorKind::PartyBuilder(e) => Some(e),
+            ErrorKind::AddressBuilder(e) => Some(e),
+            ErrorKind::LineItemBuilder(e) => Some(e),
+            ErrorKind::InvalidLogo { source, .. } => Some(source),
+            ErrorKind::QrCode(e) => Some(e.as_ref()),
+            ErrorKind::ChromedriverLaunch(e) => Some(e),
+            #[cfg(feature = "yaml")]
+            ErrorKind::Yaml(e) => Some(e),
+            #[cfg(feature = "toml")]
+            ErrorKind::Toml(e) => Some(e),
+            #[cfg(feature = "bundle")]
+            ErrorKind::Pdf(e) => Some(e),
+            #[cfg(feature = "bundle")]
+            ErrorKind::Zip(e) => Some(e),
+            ErrorKind::PortInUse(_)
+            | ErrorKind::ChromedriverExited
+            | ErrorKind::ChromedriverNotListening(_)
+            | ErrorKind::Invalid(_)
+            | ErrorKind::Validation(_)
+            | ErrorKind::Other(_) => None,
+        }
+    }
+}
+
 /// Trait providing a convenience method to add context to [`Result<T, Error>`].
 pub trait AddContext<T> {
     fn add_context(self, ctx: &str) -> Result<T, Error>;
@@ -38,80 +337,200 @@ pub trait AddContext<T> {
 
 impl Debug for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self)
+        for context in self.context.iter().rev() {
+            write!(f, "{context} -> ")?;
+        }
+        write!(f, "{}", self.root_cause())
     }
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut context = self.context.clone();
-        context.reverse();
-        let context = if context.is_empty() {
-            String::from("no context")
-        } else {
-            context.join(" -> ")
+        if !f.alternate() {
+            for context in self.context.iter().rev() {
+                write!(f, "{context} -> ")?;
+            }
+            return write!(f, "{}", self.kind);
+        }
+
+        let mut frames = self.context.iter().rev().map(String::as_str);
+        let Some(outermost) = frames.next() else {
+            return write!(f, "{}", self.kind);
         };
-        write!(f, "{context}")
+        write!(f, "{outermost}\n\nCaused by:")?;
+        let root = self.kind.to_string();
+        let mut causes = frames
+            .map(String::from)
+            .chain(std::iter::once(root))
+            .collect::<Vec<_>>();
+        let mut source = std::error::Error::source(self);
+        while let Some(e) = source {
+            causes.push(e.to_string());
+            source = e.source();
+        }
+        for (i, cause) in causes.iter().enumerate() {
+            write!(f, "\n    {i}: {cause}")?;
+        }
+        Ok(())
     }
 }
 
-impl std::error::Error for Error {}
-
-impl From<std::io::Error> for Error {
-    fn from(value: std::io::Error) -> Self {
-        Error {
-            context: vec![format!("{:?}", value)],
-            kind: ErrorKind::Io(value),
-        }
+impl std::error::Error for Error {
+    /// The error wrapped by the kind, such as the [`std::io::Error`] of [`ErrorKind::Io`]
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.kind.wrapped()
     }
 }
 
-impl From<fantoccini::error::PrintConfigurationError> for Error {
-    fn from(value: fantoccini::error::PrintConfigurationError) -> Self {
-        Error {
-            context: vec![format!("{:?}", value)],
-            kind: ErrorKind::FantocciniPrintError(value),
-        }
-    }
+/// Implement `From` for the errors wrapped by an [`ErrorKind`] variant
+macro_rules! from_error {
+    ($($(#[$meta:meta])* $error:ty => $variant:ident),* $(,)?) => {
+        $(
+            $(#[$meta])*
+            impl From<$error> for Error {
+                fn from(value: $error) -> Self {
+                    Error::new(ErrorKind::$variant(value))
+                }
+            }
+        )*
+    };
 }
 
-impl From<fantoccini::error::NewSessionError> for Error {
-    fn from(value: fantoccini::error::NewSessionError) -> Self {
+from_error!(
+    std::io::Error => Io,
+    fantoccini::error::PrintConfigurationError => FantocciniPrintError,
+    fantoccini::error::NewSessionError => FantocciniNewSession,
+    fantoccini::error::CmdError => FantocciniCmdError,
+    askama::Error => Askama,
+    csv::Error => Csv,
+    serde_json::Error => Json,
+    InvoiceBuilderError => InvoiceBuilder,
+    PartyBuilderError => PartyBuilder,
+    AddressBuilderError => AddressBuilder,
+    LineItemBuilderError => LineItemBuilder,
+    #[cfg(feature = "yaml")]
+    serde_yaml::Error => Yaml,
+    #[cfg(feature = "toml")]
+    toml::de::Error => Toml,
+    #[cfg(feature = "bundle")]
+    lopdf::Error => Pdf,
+    #[cfg(feature = "bundle")]
+    zip::result::ZipError => Zip,
+    String => Other,
+);
+
+impl Error {
+    /// Create an error of the given kind without any context yet
+    pub fn new(kind: ErrorKind) -> Self {
         Error {
-            context: vec![format!("{:?}", value)],
-            kind: ErrorKind::FantocciniNewSession(value),
+            kind,
+            context: Vec::new(),
+            details: None,
         }
     }
-}
 
-impl From<fantoccini::error::CmdError> for Error {
-    fn from(value: fantoccini::error::CmdError) -> Self {
-        Error {
-            context: vec![format!("{:?}", value)],
-            kind: ErrorKind::FantocciniCmdError(value),
-        }
+    /// The error for input values that cannot be used
+    pub fn invalid(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Invalid(message.into()))
     }
-}
 
-impl From<askama::Error> for Error {
-    fn from(value: askama::Error) -> Self {
-        Error {
-            context: vec![format!("{:?}", value)],
-            kind: ErrorKind::Askama(value),
+    /// The error for a logo file that could not be read
+    pub fn invalid_logo(path: impl Into<PathBuf>, source: std::io::Error) -> Self {
+        Self::new(ErrorKind::InvalidLogo {
+            path: path.into(),
+            source,
+        })
+    }
+
+    /// The error for a payment link that could not be drawn as a QR code
+    pub fn qr_code(source: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self::new(ErrorKind::QrCode(source.into()))
+    }
+
+    /// The context messages added so far, innermost first. The root cause is not included, it is
+    /// described by `kind`
+    pub fn context(&self) -> &[String] {
+        &self.context
+    }
+
+    /// The message of the kind followed by the message of each error it wraps, separated by
+    /// `: `, such as `cannot read logo './logo.png': No such file or directory`
+    pub fn root_cause(&self) -> String {
+        let mut message = self.kind.to_string();
+        let mut source = std::error::Error::source(self);
+        while let Some(e) = source {
+            message.push_str(&format!(": {e}"));
+            source = e.source();
         }
+        message
     }
-}
 
-impl From<String> for Error {
-    fn from(value: String) -> Self {
-        Error {
-            context: vec![value.to_string()],
-            kind: ErrorKind::Other(value),
+    /// The id of the invoice the error is about, if known
+    pub fn invoice_id(&self) -> Option<&str> {
+        self.details.as_ref()?.invoice_id.as_deref()
+    }
+
+    /// Where in the input the error was found. The line and column of JSON errors are filled in
+    /// from the JSON parser when they were not recorded otherwise
+    pub fn location(&self) -> Location {
+        let mut location = self
+            .details
+            .as_ref()
+            .map(|details| details.location.clone())
+            .unwrap_or_default();
+        if let ErrorKind::Json(e) = &self.kind
+            && location.line.is_none()
+            && e.line() > 0
+        {
+            location.line = Some(e.line());
+            location.column = Some(e.column());
         }
+        location
+    }
+
+    fn details_mut(&mut self) -> &mut Details {
+        self.details.get_or_insert_with(Box::default)
+    }
+
+    fn location_mut(&mut self) -> &mut Location {
+        &mut self.details_mut().location
+    }
+
+    /// Record the invoice the error is about, unless one was already recorded closer to the cause
+    pub fn with_invoice_id(mut self, id: impl Into<String>) -> Self {
+        self.details_mut()
+            .invoice_id
+            .get_or_insert_with(|| id.into());
+        self
+    }
+
+    /// Record the input file the error was found in, unless one was already recorded
+    pub fn with_source(mut self, source: impl Into<String>) -> Self {
+        self.location_mut()
+            .source
+            .get_or_insert_with(|| source.into());
+        self
+    }
+
+    /// Record the input line or row the error was found on, unless one was already recorded
+    pub fn at_line(mut self, line: usize) -> Self {
+        self.location_mut().line.get_or_insert(line);
+        self
+    }
+
+    /// Record the column within the input line the error was found at, unless one was already
+    /// recorded
+    pub fn at_column(mut self, column: usize) -> Self {
+        self.location_mut().column.get_or_insert(column);
+        self
+    }
+
+    /// Record the position of the invoice in its batch, unless one was already recorded
+    pub fn at_index(mut self, index: usize) -> Self {
+        self.location_mut().index.get_or_insert(index);
+        self
     }
-}
 
-impl Error {
     /// Add more context to the given error. This context will ultimately be displayed to the user
     /// and could be useful for correcting bad input or filing a help ticket.
     ///
@@ -121,14 +540,10 @@ impl Error {
     ///
     /// # Arguments
     /// * `context` - Any additional information that would be useful for the user to see if the
-    /// error is surfaced to them
-    pub fn add_context(self, context: &str) -> Error {
-        let mut existing = self.context.clone();
-        existing.push(context.to_string());
-        Self {
-            context: existing,
-            ..self
-        }
+    ///   error is surfaced to them
+    pub fn add_context(mut self, context: &str) -> Error {
+        self.context.push(context.to_string());
+        self
     }
 }
 
@@ -140,3 +555,138 @@ impl<T> AddContext<T> for Result<T, Error> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error as _;
+
+    use super::*;
+
+    #[test]
+    fn test_source_chain() {
+        let e = Error::invalid_logo("./logo.png", std::io::Error::other("gone"))
+            .add_context("checking logo")
+            .add_context("rendering invoice 7");
+        assert_eq!(
+            e.to_string(),
+            "rendering invoice 7 -> checking logo -> cannot read logo './logo.png'"
+        );
+        assert_eq!(e.context(), ["checking logo", "rendering invoice 7"]);
+        assert_eq!(e.root_cause(), "cannot read logo './logo.png': gone");
+        assert_eq!(
+            format!("{e:?}"),
+            "rendering invoice 7 -> checking logo -> cannot read logo './logo.png': gone"
+        );
+        let source = e.source().unwrap();
+        assert_eq!(
+            source.downcast_ref::<std::io::Error>().unwrap().to_string(),
+            "gone"
+        );
+        assert!(source.source().is_none());
+        assert_eq!(
+            format!("{e:#}"),
+            "rendering invoice 7\n\nCaused by:\n    0: checking logo\n    1: cannot read logo './logo.png'\n    2: gone"
+        );
+
+        #[derive(Debug)]
+        struct Encoding(std::io::Error);
+
+        impl Display for Encoding {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "link too long")
+            }
+        }
+
+        impl std::error::Error for Encoding {
+            fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+                Some(&self.0)
+            }
+        }
+
+        let e = Error::qr_code(Encoding(std::io::Error::other("out of space")))
+            .add_context("drawing invoice 7");
+        let mut chain = vec![e.to_string()];
+        let mut source = e.source();
+        while let Some(cause) = source {
+            chain.push(cause.to_string());
+            source = cause.source();
+        }
+        assert_eq!(
+            chain,
+            [
+                "drawing invoice 7 -> cannot draw payment QR code",
+                "link too long",
+                "out of space"
+            ]
+        );
+        assert!(e.source().unwrap().downcast_ref::<Encoding>().is_some());
+        assert_eq!(
+            format!("{e:#}"),
+            "drawing invoice 7\n\nCaused by:\n    0: cannot draw payment QR code\n    1: link too long\n    2: out of space"
+        );
+
+        let json = serde_json::from_str::<Vec<u8>>("[1,").unwrap_err();
+        let message = json.to_string();
+        let e = Error::from(json);
+        assert_eq!(e.to_string(), "invalid JSON");
+        let source = e.source().unwrap();
+        assert_eq!(
+            source
+                .downcast_ref::<serde_json::Error>()
+                .unwrap()
+                .to_string(),
+            message
+        );
+
+        let e = Error::from(String::from("plain"));
+        assert_eq!(e.kind.category(), ErrorCategory::Other);
+        assert!(e.source().is_none());
+        assert_eq!(format!("{e}"), "plain");
+        assert_eq!(format!("{e:?}"), "plain");
+        assert_eq!(format!("{e:#}"), "plain");
+    }
+
+    #[test]
+    fn test_chromedriver_start_failures_are_transient() {
+        let e = Error::new(ErrorKind::ChromedriverExited).add_context("starting chromedriver");
+        assert!(e.kind.is_transient());
+        assert_eq!(e.kind.name(), "chromedriver_exited");
+        assert_eq!(e.kind.category(), ErrorCategory::Browser);
+        assert!(e.source().is_none());
+        assert_eq!(
+            e.to_string(),
+            "starting chromedriver -> chromedriver stopped unexpectedly"
+        );
+
+        let e = Error::new(ErrorKind::ChromedriverNotListening(4444));
+        assert!(e.kind.is_transient());
+        assert_eq!(e.kind.name(), "chromedriver_not_listening");
+        assert_eq!(e.to_string(), "chromedriver failed to bind to port 4444");
+
+        let e = Error::new(ErrorKind::ChromedriverLaunch(std::io::Error::from(
+            std::io::ErrorKind::NotFound,
+        )));
+        assert!(!e.kind.is_transient());
+    }
+
+    #[test]
+    fn test_location() {
+        let e = Error::from(serde_json::from_str::<Vec<u8>>("[\n1,\n").unwrap_err())
+            .with_source("invoices.json")
+            .with_invoice_id("7")
+            .with_invoice_id("8");
+        assert_eq!(e.kind.name(), "json");
+        assert_eq!(e.kind.category(), ErrorCategory::Input);
+        assert_eq!(e.invoice_id(), Some("7"));
+        assert_eq!(
+            e.location(),
+            Location {
+                source: Some(String::from("invoices.json")),
+                line: Some(3),
+                column: Some(0),
+                index: None,
+            }
+        );
+        assert!(Error::invalid("x").location().is_empty());
+    }
+}
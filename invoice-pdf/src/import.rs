@@ -0,0 +1,37 @@
+//! Readers for invoice data that does not arrive as the JSON array the CLI reads by default.
+//!
+//! Each submodule understands one external format and produces the same [`crate::Invoice`]
+//! values that deserializing the JSON layout would. The `yaml` and `toml` readers are only
+//! available with the features of the same name.
+
+pub mod abc;
+pub mod csv;
+pub mod ndjson;
+#[cfg(feature = "toml")]
+pub mod toml;
+#[cfg(feature = "yaml")]
+pub mod yaml;
+
+/// Deserialize each value in the JSON layout into an [`crate::Invoice`], naming the position and id of
+/// the first one that fails
+#[cfg(any(feature = "yaml", feature = "toml"))]
+fn invoices_from_values(
+    values: Vec<serde_json::Value>,
+) -> Result<Vec<crate::Invoice>, crate::Error> {
+    use crate::error::AddContext;
+
+    values
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| {
+            let id = value
+                .get("id")
+                .and_then(|id| id.as_str())
+                .unwrap_or("UNKNOWN")
+                .to_string();
+            serde_json::from_value(value)
+                .map_err(|e| crate::Error::from(e).at_index(i).with_invoice_id(&id))
+                .add_context(&format!("deserializing invoice {i} with id '{id}'"))
+        })
+        .collect()
+}
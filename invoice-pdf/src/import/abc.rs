@@ -0,0 +1,375 @@
+//! Read invoices from ABC Accounting invoice export files.
+//!
+//! An export is plain text with one tab separated record per line. The first field of every
+//! record is a two letter record type and the remaining fields depend on the type. Trailing empty
+//! fields may be left off and blank lines are ignored.
+//!
+//! | Type | Fields |
+//! |------|--------|
+//! | `CO` | company name, line 1, line 2, city, state, zip, phone, email |
+//! | `IN` | invoice number, date, due date, account number, purchase order, amount paid |
+//! | `BT` | bill to name, line 1, line 2, city, state, zip, phone, email |
+//! | `ST` | ship to name, line 1, line 2, city, state, zip, phone, email |
+//! | `LI` | sku, upc, description, quantity, unit price |
+//! | `RM` | remark text for the line item directly above it |
+//!
+//! The `CO` record must come first and becomes the sender of every invoice in the file. Each `IN`
+//! record starts a new invoice, and the `BT`, `ST`, `LI` and `RM` records that follow it belong to
+//! that invoice. Dates are written `MM/DD/YY` and are taken to be midnight local time. Amounts may
+//! contain thousands separators and use a trailing `-` for negative values, as in `1,018.44-`.
+
+use std::{
+    io::{BufRead, BufReader, Read},
+    str::FromStr,
+};
+
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, FixedOffset, Local, NaiveDate, TimeZone};
+use gtin::Gtin;
+
+use crate::{
+    AddressBuilder, Invoice, InvoiceBuilder, LineItemBuilder, Party, PartyBuilder,
+    error::AddContext,
+};
+
+/// A single line of an export split into its fields
+struct Record {
+    line: usize,
+    kind: String,
+    fields: Vec<String>,
+}
+
+impl Record {
+    fn parse(line: usize, raw: &str) -> Self {
+        let mut parts = raw.split('\t').map(|f| f.trim().to_string());
+        let kind = parts.next().unwrap_or_default().to_uppercase();
+        Self {
+            line,
+            kind,
+            fields: parts.collect(),
+        }
+    }
+
+    fn error(&self, msg: impl std::fmt::Display) -> crate::Error {
+        crate::Error::invalid(format!("line {}, {} record: {msg}", self.line, self.kind))
+            .at_line(self.line)
+    }
+
+    /// Point an error raised while handling this record, such as a builder error, at its line
+    fn locate(&self, error: impl Into<crate::Error>) -> crate::Error {
+        error
+            .into()
+            .at_line(self.line)
+            .add_context(&format!("line {}, {} record", self.line, self.kind))
+    }
+
+    /// Get the field at `i`, treating empty fields as missing
+    fn get(&self, i: usize) -> Option<&str> {
+        self.fields
+            .get(i)
+            .map(String::as_str)
+            .filter(|f| !f.is_empty())
+    }
+
+    fn required(&self, i: usize, name: &str) -> Result<&str, crate::Error> {
+        self.get(i)
+            .ok_or_else(|| self.error(format!("missing {name}")))
+    }
+
+    fn amount(&self, i: usize, name: &str) -> Result<Option<BigDecimal>, crate::Error> {
+        self.get(i)
+            .map(|raw| self.parse_amount(raw, name))
+            .transpose()
+    }
+
+    fn parse_amount(&self, raw: &str, name: &str) -> Result<BigDecimal, crate::Error> {
+        let cleaned = raw.replace(',', "");
+        let amount = match cleaned.strip_suffix('-') {
+            Some(abs) => BigDecimal::from_str(abs).map(|d| -d),
+            None => BigDecimal::from_str(&cleaned),
+        };
+        amount.map_err(|_| self.error(format!("{name} '{raw}' is not a number")))
+    }
+
+    fn date(&self, i: usize, name: &str) -> Result<Option<DateTime<FixedOffset>>, crate::Error> {
+        let Some(raw) = self.get(i) else {
+            return Ok(None);
+        };
+        let date = NaiveDate::parse_from_str(raw, "%m/%d/%y")
+            .map_err(|_| self.error(format!("{name} '{raw}' is not a MM/DD/YY date")))?;
+        let local = Local
+            .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap_or_default())
+            .earliest()
+            .ok_or_else(|| self.error(format!("{name} '{raw}' does not exist in local time")))?;
+        Ok(Some(local.fixed_offset()))
+    }
+
+    /// Build a [`Party`] from a `CO`, `BT` or `ST` record
+    fn party(&self) -> Result<Party, crate::Error> {
+        let mut builder = PartyBuilder::default().name(self.required(0, "name")?);
+        if let Some(line1) = self.get(1) {
+            let mut address = AddressBuilder::default()
+                .line1(line1)
+                .city(self.required(3, "city")?)
+                .province_code(self.required(4, "state")?)
+                .postal_code(self.required(5, "zip")?);
+            if let Some(line2) = self.get(2) {
+                address = address.line2(line2);
+            }
+            builder = builder.address(
+                address
+                    .build()
+                    .map_err(|e| self.locate(e))
+                    .add_context("building address")?,
+            );
+        }
+        if let Some(phone) = self.get(6) {
+            builder = builder.phone(phone);
+        }
+        if let Some(email) = self.get(7) {
+            builder = builder.email(email);
+        }
+        builder
+            .build()
+            .map_err(|e| self.locate(e))
+            .add_context("building party")
+    }
+}
+
+/// An invoice whose records are still being read
+struct PendingInvoice {
+    line: usize,
+    id: String,
+    builder: InvoiceBuilder,
+    has_bill_to: bool,
+    current_line_item: Option<LineItemBuilder>,
+}
+
+impl PendingInvoice {
+    fn new(record: &Record, sender: Party) -> Result<Self, crate::Error> {
+        let id = record.required(0, "invoice number")?.to_string();
+        let mut builder = InvoiceBuilder::default().id(id.as_str()).sender(sender);
+        if let Some(created) = record.date(1, "date")? {
+            builder = builder.created_datetime(created);
+        }
+        if let Some(due) = record.date(2, "due date")? {
+            builder = builder.net_due_datetime(due);
+        }
+        if let Some(acct_id) = record.get(3) {
+            builder = builder.acct_id(acct_id);
+        }
+        if let Some(purchase_order) = record.get(4) {
+            builder = builder.purchase_order(purchase_order);
+        }
+        if let Some(paid) = record.amount(5, "amount paid")? {
+            builder = builder.paid(paid);
+        }
+        Ok(Self {
+            line: record.line,
+            id,
+            builder,
+            has_bill_to: false,
+            current_line_item: None,
+        })
+    }
+
+    fn add_record(mut self, record: &Record) -> Result<Self, crate::Error> {
+        match record.kind.as_str() {
+            "BT" => {
+                self.builder = self.builder.bill_to(record.party()?);
+                self.has_bill_to = true;
+            }
+            "ST" => self.builder = self.builder.ship_to(record.party()?),
+            "LI" => {
+                self = self.flush_line_item()?;
+                let mut line_item = LineItemBuilder::default()
+                    .sku(record.required(0, "sku")?)
+                    .title(record.required(2, "description")?)
+                    .quantity(record.parse_amount(record.required(3, "quantity")?, "quantity")?)
+                    .price(record.parse_amount(record.required(4, "unit price")?, "unit price")?);
+                if let Some(upc) = record.get(1) {
+                    line_item = line_item.gtin(
+                        Gtin::new(upc)
+                            .map_err(|_| record.error(format!("upc '{upc}' is not valid")))?,
+                    );
+                }
+                self.current_line_item = Some(line_item);
+            }
+            "RM" => {
+                let line_item = self
+                    .current_line_item
+                    .take()
+                    .ok_or_else(|| record.error("remark does not follow a line item"))?;
+                self.current_line_item =
+                    Some(line_item.add_remark(record.get(0).unwrap_or_default()));
+            }
+            _ => return Err(record.error("unknown record type")),
+        }
+        Ok(self)
+    }
+
+    fn flush_line_item(mut self) -> Result<Self, crate::Error> {
+        if let Some(line_item) = self.current_line_item.take() {
+            self.builder = self.builder.add_line(
+                line_item
+                    .build()
+                    .map_err(crate::Error::from)
+                    .add_context(&format!("building line item of invoice '{}'", self.id))?,
+            );
+        }
+        Ok(self)
+    }
+
+    fn build(self) -> Result<Invoice, crate::Error> {
+        if !self.has_bill_to {
+            return Err(crate::Error::invalid(format!(
+                "line {}, IN record: invoice '{}' has no BT record",
+                self.line, self.id
+            ))
+            .at_line(self.line)
+            .with_invoice_id(&self.id));
+        }
+        let id = self.id.clone();
+        self.flush_line_item()?
+            .builder
+            .build()
+            .map_err(crate::Error::from)
+            .add_context(&format!("building invoice '{id}'"))
+    }
+}
+
+/// Read every invoice from an ABC Accounting invoice export
+///
+/// # Arguments
+/// - `reader` Source of the export. See the [module documentation](self) for the layout
+///
+/// # Returns
+/// - The invoices in the order they appear in the export
+///
+/// # Errors
+/// - [`crate::Error`] if the export cannot be read, records appear out of order, or a field
+///   cannot be parsed. The message names the line and record type at fault
+pub fn read_invoices<R: Read>(reader: R) -> Result<Vec<Invoice>, crate::Error> {
+    let mut sender: Option<Party> = None;
+    let mut pending: Option<PendingInvoice> = None;
+    let mut invoices = Vec::new();
+    for (i, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line
+            .map_err(crate::Error::from)
+            .add_context("reading ABC export")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record = Record::parse(i + 1, &line);
+        match record.kind.as_str() {
+            "CO" => {
+                if sender.is_some() {
+                    return Err(record.error("only one company record is allowed"));
+                }
+                sender = Some(record.party().add_context("reading company record")?);
+            }
+            "IN" => {
+                let company = sender
+                    .clone()
+                    .ok_or_else(|| record.error("invoice appears before the company record"))?;
+                if let Some(previous) = pending.replace(PendingInvoice::new(&record, company)?) {
+                    invoices.push(previous.build()?);
+                }
+            }
+            _ => {
+                let invoice = pending
+                    .take()
+                    .ok_or_else(|| record.error("record appears before any invoice"))?;
+                pending = Some(invoice.add_record(&record)?);
+            }
+        }
+    }
+    if let Some(last) = pending {
+        invoices.push(last.build()?);
+    }
+    Ok(invoices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_single_invoice() {
+        let invoices =
+            read_invoices(include_str!("../../fixtures/abc/single.txt").as_bytes()).unwrap();
+        assert_eq!(invoices.len(), 1);
+        let inv = &invoices[0];
+        assert_eq!(&inv.id(), "104552");
+        assert_eq!(inv.sender().name(), "Hometown Hardware");
+        assert_eq!(
+            inv.sender().address().as_ref().unwrap().city(),
+            "Springfield"
+        );
+        assert_eq!(inv.bill_to().name(), "Smith Construction");
+        assert_eq!(inv.ship_to().as_ref().unwrap().name(), "Smith Job Site");
+        assert_eq!(inv.acct_id().as_deref(), Some("SMI100"));
+        assert_eq!(inv.purchase_order().as_deref(), Some("PO-778"));
+        assert_eq!(
+            inv.created_datetime().date_naive().to_string(),
+            "2026-02-10"
+        );
+        assert_eq!(
+            inv.net_due_datetime().date_naive().to_string(),
+            "2026-03-10"
+        );
+        assert_eq!(&inv.paid().to_string(), "1000.00");
+
+        let lines = inv.line_items();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(&lines[0].sku(), "2X4-8");
+        assert_eq!(&lines[0].gtin().unwrap().to_string(), "00082657543338");
+        assert_eq!(
+            lines[0].remarks(),
+            &vec!["KD stud grade", "Pulled from yard"]
+        );
+        assert!(lines[1].gtin().is_none());
+        assert!(lines[1].remarks().is_empty());
+        assert_eq!(&lines[2].price().to_string(), "-12.500");
+        assert_eq!(&inv.total().to_string(), "1040.68");
+    }
+
+    #[test]
+    fn test_read_batch() {
+        let invoices =
+            read_invoices(include_str!("../../fixtures/abc/batch.txt").as_bytes()).unwrap();
+        let ids: Vec<_> = invoices.iter().map(Invoice::id).collect();
+        assert_eq!(ids, vec!["104553", "104554"]);
+        assert!(
+            invoices
+                .iter()
+                .all(|i| i.sender().name() == "Hometown Hardware")
+        );
+        assert!(invoices[0].ship_to().is_none());
+        assert_eq!(
+            invoices[1].line_items()[0].remarks(),
+            &vec!["Special order"]
+        );
+        assert_eq!(&invoices[1].paid().to_string(), "0");
+    }
+
+    #[test]
+    fn test_errors_name_the_line() {
+        let e = read_invoices("IN\t1\n".as_bytes()).unwrap_err();
+        assert!(e.to_string().contains("line 1, IN record"), "{e}");
+
+        let e = read_invoices("CO\tStore\nIN\t1\nBT\tBob\nLI\tA\t\tThing\tlots\t1\n".as_bytes())
+            .unwrap_err();
+        assert!(
+            e.to_string()
+                .contains("line 4, LI record: quantity 'lots' is not a number"),
+            "{e}"
+        );
+
+        let e = read_invoices("CO\tStore\nIN\t1\nLI\tA\t\tThing\t1\t1\n".as_bytes()).unwrap_err();
+        assert!(e.to_string().contains("has no BT record"), "{e}");
+
+        let e = read_invoices("CO\tStore\nIN\t1\nBT\tBob\nRM\toops\n".as_bytes()).unwrap_err();
+        assert!(e.to_string().contains("line 4, RM record"), "{e}");
+    }
+}
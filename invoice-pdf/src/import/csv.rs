@@ -0,0 +1,546 @@
+//! Read invoices from spreadsheet style CSV files.
+//!
+//! The first row must be a header naming each column, in any order. Every following row
+//! describes one line item, and rows sharing an `invoice_id` are grouped into a single
+//! [`Invoice`] in the order the ids first appear. Invoice level columns (dates, parties, `paid`,
+//! etc.) only need to be filled in on one row of each invoice. They may be repeated on the other
+//! rows as long as the values agree.
+//!
+//! # Columns
+//! - `invoice_id` (required)
+//! - `created_datetime`, `net_due_datetime` as RFC 3339 datetimes
+//! - `acct_id`, `purchase_order`, `payment_url`, `logo`
+//! - `paid` as a decimal amount
+//! - `terms` as payment terms such as `Net 30` or `2/10 Net 30`
+//! - `post_script`, where every non-empty value of the invoice is added in row order
+//! - `sender_*`, `bill_to_*` and `ship_to_*` party columns, where `*` is one of `name`, `phone`,
+//!   `email`, `line1`, `line2`, `city`, `province_code` or `postal_code`. `sender_name` and
+//!   `bill_to_name` are required
+//! - `sku`, `title`, `quantity`, `price` (required) and `gtin` for the line item itself
+//!
+//! Errors point at the offending row (the line number in the file, counting the header as row 1)
+//! and column so they can be fixed directly in the spreadsheet.
+
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    io::Read,
+    str::FromStr,
+};
+
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, FixedOffset};
+use csv::{ReaderBuilder, StringRecord, Trim};
+use gtin::Gtin;
+
+use crate::{
+    Address, AddressBuilder, Invoice, InvoiceBuilder, LineItem, LineItemBuilder, Party,
+    PartyBuilder, error::AddContext, terms::PaymentTerms,
+};
+
+const INVOICE_COLUMNS: [&str; 8] = [
+    "created_datetime",
+    "net_due_datetime",
+    "acct_id",
+    "purchase_order",
+    "payment_url",
+    "logo",
+    "paid",
+    "terms",
+];
+const PARTY_PREFIXES: [&str; 3] = ["sender", "bill_to", "ship_to"];
+const PARTY_COLUMNS: [&str; 3] = ["name", "phone", "email"];
+const ADDRESS_COLUMNS: [&str; 5] = ["line1", "line2", "city", "province_code", "postal_code"];
+const LINE_ITEM_COLUMNS: [&str; 5] = ["sku", "title", "quantity", "price", "gtin"];
+const REQUIRED_COLUMNS: [&str; 7] = [
+    "invoice_id",
+    "sender_name",
+    "bill_to_name",
+    "sku",
+    "title",
+    "quantity",
+    "price",
+];
+
+/// Build the error reported for a single bad cell
+fn cell_error(row: u64, column: &str, msg: impl std::fmt::Display) -> crate::Error {
+    crate::Error::invalid(format!("row {row}, column '{column}': {msg}")).at_line(row as usize)
+}
+
+fn parse_decimal(row: u64, column: &str, value: &str) -> Result<BigDecimal, crate::Error> {
+    BigDecimal::from_str(value)
+        .map_err(|_| cell_error(row, column, format!("'{value}' is not a decimal number")))
+}
+
+fn parse_datetime(
+    row: u64,
+    column: &str,
+    value: &str,
+) -> Result<DateTime<FixedOffset>, crate::Error> {
+    DateTime::parse_from_rfc3339(value).map_err(|_| {
+        cell_error(
+            row,
+            column,
+            format!("'{value}' is not an RFC 3339 datetime"),
+        )
+    })
+}
+
+/// Names every column that may appear in the header
+fn known_columns() -> Vec<String> {
+    let mut known: Vec<String> = ["invoice_id", "post_script"]
+        .iter()
+        .chain(INVOICE_COLUMNS.iter())
+        .chain(LINE_ITEM_COLUMNS.iter())
+        .map(|c| c.to_string())
+        .collect();
+    for prefix in PARTY_PREFIXES {
+        for column in PARTY_COLUMNS.iter().chain(ADDRESS_COLUMNS.iter()) {
+            known.push(format!("{prefix}_{column}"));
+        }
+    }
+    known
+}
+
+/// Maps header names to their position in each record
+struct Columns {
+    positions: HashMap<String, usize>,
+}
+
+impl Columns {
+    fn from_header(header: &StringRecord) -> Result<Self, crate::Error> {
+        let known = known_columns();
+        let mut positions = HashMap::new();
+        for (i, name) in header.iter().enumerate() {
+            let name = name.to_lowercase();
+            if !known.contains(&name) {
+                return Err(cell_error(1, &name, "unknown column"));
+            }
+            if positions.insert(name.clone(), i).is_some() {
+                return Err(cell_error(1, &name, "column appears more than once"));
+            }
+        }
+        if let Some(missing) = REQUIRED_COLUMNS
+            .iter()
+            .find(|c| !positions.contains_key(**c))
+        {
+            return Err(crate::Error::invalid(format!(
+                "row 1: missing required column '{missing}'"
+            ))
+            .at_line(1));
+        }
+        Ok(Self { positions })
+    }
+
+    /// Get the trimmed value of `column` in `record`, treating empty cells as missing
+    fn get<'r>(&self, record: &'r StringRecord, column: &str) -> Option<&'r str> {
+        let i = *self.positions.get(column)?;
+        record.get(i).filter(|v| !v.is_empty())
+    }
+
+    /// Names of the header columns that describe the invoice rather than a line item
+    fn invoice_level(&self) -> impl Iterator<Item = &String> {
+        self.positions.keys().filter(|c| {
+            INVOICE_COLUMNS.contains(&c.as_str())
+                || PARTY_PREFIXES
+                    .iter()
+                    .any(|p| c.starts_with(&format!("{p}_")))
+        })
+    }
+}
+
+/// An invoice whose rows are still being collected
+struct PendingInvoice {
+    id: String,
+    first_row: u64,
+    /// Invoice level values keyed by column along with the row they were first seen on
+    fields: HashMap<String, (String, u64)>,
+    post_scripts: Vec<String>,
+    line_items: Vec<LineItem>,
+}
+
+impl PendingInvoice {
+    fn new(id: &str, first_row: u64) -> Self {
+        Self {
+            id: id.to_string(),
+            first_row,
+            fields: HashMap::new(),
+            post_scripts: Vec::new(),
+            line_items: Vec::new(),
+        }
+    }
+
+    fn add_row(
+        &mut self,
+        row: u64,
+        record: &StringRecord,
+        columns: &Columns,
+    ) -> Result<(), crate::Error> {
+        for column in columns.invoice_level() {
+            let Some(value) = columns.get(record, column) else {
+                continue;
+            };
+            match self.fields.entry(column.to_string()) {
+                Entry::Vacant(e) => {
+                    e.insert((value.to_string(), row));
+                }
+                Entry::Occupied(e) => {
+                    let (existing, existing_row) = e.get();
+                    if existing != value {
+                        return Err(cell_error(
+                            row,
+                            column,
+                            format!(
+                                "'{value}' conflicts with '{existing}' from row {existing_row} of invoice '{}'",
+                                self.id
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+        if let Some(post_script) = columns.get(record, "post_script") {
+            self.post_scripts.push(post_script.to_string());
+        }
+        self.line_items
+            .push(line_item_from_row(row, record, columns)?);
+        Ok(())
+    }
+
+    /// Error for an invoice level `column` that was never filled in
+    fn missing(&self, column: &str) -> crate::Error {
+        cell_error(
+            self.first_row,
+            column,
+            format!("missing value for invoice '{}'", self.id),
+        )
+    }
+
+    fn field(&self, column: &str) -> Option<(&str, u64)> {
+        self.fields.get(column).map(|(v, row)| (v.as_str(), *row))
+    }
+
+    fn party(&self, prefix: &str) -> Result<Option<Party>, crate::Error> {
+        let has_any = PARTY_COLUMNS
+            .iter()
+            .chain(ADDRESS_COLUMNS.iter())
+            .any(|c| self.fields.contains_key(&format!("{prefix}_{c}")));
+        if !has_any {
+            return Ok(None);
+        }
+
+        let name_column = format!("{prefix}_name");
+        let Some((name, _)) = self.field(&name_column) else {
+            return Err(self.missing(&name_column));
+        };
+        let mut builder = PartyBuilder::default().name(name);
+        if let Some((phone, _)) = self.field(&format!("{prefix}_phone")) {
+            builder = builder.phone(phone);
+        }
+        if let Some((email, _)) = self.field(&format!("{prefix}_email")) {
+            builder = builder.email(email);
+        }
+        if let Some(address) = self.address(prefix)? {
+            builder = builder.address(address);
+        }
+        builder
+            .build()
+            .map_err(crate::Error::from)
+            .add_context(&format!("building {prefix} party"))
+            .map(Some)
+    }
+
+    fn address(&self, prefix: &str) -> Result<Option<Address>, crate::Error> {
+        let Some(row) = ADDRESS_COLUMNS
+            .iter()
+            .find_map(|c| self.field(&format!("{prefix}_{c}")).map(|(_, row)| row))
+        else {
+            return Ok(None);
+        };
+        let required = |column: &str| -> Result<&str, crate::Error> {
+            let column = format!("{prefix}_{column}");
+            self.field(&column).map(|(v, _)| v).ok_or_else(|| {
+                cell_error(
+                    row,
+                    &column,
+                    format!("missing value for the address of invoice '{}'", self.id),
+                )
+            })
+        };
+        let mut builder = AddressBuilder::default()
+            .line1(required("line1")?)
+            .city(required("city")?)
+            .province_code(required("province_code")?)
+            .postal_code(required("postal_code")?);
+        if let Some((line2, _)) = self.field(&format!("{prefix}_line2")) {
+            builder = builder.line2(line2);
+        }
+        builder
+            .build()
+            .map_err(crate::Error::from)
+            .add_context(&format!("building {prefix} address"))
+            .map(Some)
+    }
+
+    fn build(self) -> Result<Invoice, crate::Error> {
+        let sender = self
+            .party("sender")?
+            .ok_or_else(|| self.missing("sender_name"))?;
+        let bill_to = self
+            .party("bill_to")?
+            .ok_or_else(|| self.missing("bill_to_name"))?;
+        let mut builder = InvoiceBuilder::default()
+            .id(self.id.as_str())
+            .sender(sender)
+            .bill_to(bill_to);
+        if let Some(ship_to) = self.party("ship_to")? {
+            builder = builder.ship_to(ship_to);
+        }
+        if let Some((v, row)) = self.field("created_datetime") {
+            builder = builder.created_datetime(parse_datetime(row, "created_datetime", v)?);
+        }
+        if let Some((v, row)) = self.field("net_due_datetime") {
+            builder = builder.net_due_datetime(parse_datetime(row, "net_due_datetime", v)?);
+        }
+        if let Some((v, row)) = self.field("paid") {
+            builder = builder.paid(parse_decimal(row, "paid", v)?);
+        }
+        if let Some((v, row)) = self.field("terms") {
+            let terms = PaymentTerms::from_str(v).map_err(|_| {
+                cell_error(
+                    row,
+                    "terms",
+                    format!("'{v}' is not payment terms such as 'Net 30' or '2/10 Net 30'"),
+                )
+            })?;
+            builder = builder.terms(terms);
+        }
+        if let Some((v, _)) = self.field("acct_id") {
+            builder = builder.acct_id(v);
+        }
+        if let Some((v, _)) = self.field("purchase_order") {
+            builder = builder.purchase_order(v);
+        }
+        if let Some((v, _)) = self.field("payment_url") {
+            builder = builder.payment_url(v);
+        }
+        if let Some((v, _)) = self.field("logo") {
+            builder = builder.logo(v);
+        }
+        let id = self.id.clone();
+        builder
+            .line_items(self.line_items)
+            .post_scripts(self.post_scripts)
+            .build()
+            .map_err(crate::Error::from)
+            .add_context(&format!("building invoice '{id}'"))
+    }
+}
+
+fn line_item_from_row(
+    row: u64,
+    record: &StringRecord,
+    columns: &Columns,
+) -> Result<LineItem, crate::Error> {
+    let required = |column: &str| {
+        columns
+            .get(record, column)
+            .ok_or_else(|| cell_error(row, column, "missing value"))
+    };
+    let mut builder = LineItemBuilder::default()
+        .sku(required("sku")?)
+        .title(required("title")?)
+        .quantity(parse_decimal(row, "quantity", required("quantity")?)?)
+        .price(parse_decimal(row, "price", required("price")?)?);
+    if let Some(gtin) = columns.get(record, "gtin") {
+        builder = builder.gtin(
+            Gtin::new(gtin)
+                .map_err(|_| cell_error(row, "gtin", format!("'{gtin}' is not a valid GTIN")))?,
+        );
+    }
+    builder
+        .build()
+        .map_err(crate::Error::from)
+        .add_context(&format!("building line item from row {row}"))
+}
+
+/// Read every invoice from CSV data with a header row
+///
+/// # Arguments
+/// - `reader` Source of the CSV data. See the [module documentation](self) for the columns
+///
+/// # Returns
+/// - The invoices in the order their `invoice_id` first appears
+///
+/// # Errors
+/// - [`crate::Error`] if the CSV is malformed, the header contains unknown or duplicate columns,
+///   a required column or value is missing, or a cell cannot be parsed. The message names the row
+///   and column at fault
+///
+/// # Example
+/// ```rust
+/// let data = "invoice_id,sender_name,bill_to_name,sku,title,quantity,price\n\
+///             1,Sender,Customer,A1,Widget,2,9.99\n\
+///             1,,,B2,Gadget,1,20\n";
+/// let invoices = invoice_pdf::import::csv::read_invoices(data.as_bytes()).unwrap();
+/// assert_eq!(invoices.len(), 1);
+/// assert_eq!(invoices[0].line_items().len(), 2);
+/// ```
+pub fn read_invoices<R: Read>(reader: R) -> Result<Vec<Invoice>, crate::Error> {
+    let mut reader = ReaderBuilder::new().trim(Trim::All).from_reader(reader);
+    let columns = Columns::from_header(
+        reader
+            .headers()
+            .map_err(crate::Error::from)
+            .add_context("reading csv header")?,
+    )?;
+
+    let mut order: HashMap<String, usize> = HashMap::new();
+    let mut pending: Vec<PendingInvoice> = Vec::new();
+    for record in reader.records() {
+        let record = record
+            .map_err(crate::Error::from)
+            .add_context("reading csv record")?;
+        let row = record.position().map(|p| p.line()).unwrap_or_default();
+        let Some(id) = columns.get(&record, "invoice_id") else {
+            return Err(cell_error(row, "invoice_id", "missing value"));
+        };
+        let i = *order.entry(id.to_string()).or_insert_with(|| {
+            pending.push(PendingInvoice::new(id, row));
+            pending.len() - 1
+        });
+        pending[i]
+            .add_row(row, &record, &columns)
+            .map_err(|e| e.with_invoice_id(id))?;
+    }
+
+    pending
+        .into_iter()
+        .map(|invoice| {
+            let id = invoice.id.clone();
+            invoice.build().map_err(|e| e.with_invoice_id(id))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "invoice_id,created_datetime,paid,post_script,sender_name,sender_line1,sender_city,sender_province_code,sender_postal_code,bill_to_name,bill_to_email,ship_to_name,sku,title,quantity,price,gtin";
+
+    fn read(rows: &[&str]) -> Result<Vec<Invoice>, crate::Error> {
+        let mut data = vec![HEADER];
+        data.extend_from_slice(rows);
+        read_invoices(data.join("\n").as_bytes())
+    }
+
+    #[test]
+    fn test_groups_rows_by_invoice_id() {
+        let invoices = read(&[
+            "2,2026-02-10T12:00:00+00:00,5,Thank you,Sender,1 St,City,PR,12345,Bob,bob@example.com,,A,Widget,2,9.99,082657543338",
+            "1,,,,Sender,,,,,Alice,,Alice's Shop,C,Thing,1,1,",
+            "2,,,Net 30,,,,,,,,,B,Gadget,1.5,10,",
+        ])
+        .unwrap();
+        assert_eq!(invoices.len(), 2);
+
+        let second = &invoices[0];
+        assert_eq!(&second.id(), "2");
+        assert_eq!(second.line_items().len(), 2);
+        assert_eq!(&second.total().to_string(), "34.98");
+        assert_eq!(&second.paid().to_string(), "5.00");
+        assert_eq!(second.post_scripts(), &vec!["Thank you", "Net 30"]);
+        assert_eq!(
+            second.created_datetime().to_rfc3339(),
+            "2026-02-10T12:00:00+00:00"
+        );
+        assert_eq!(
+            second.sender().address().as_ref().unwrap().postal_code(),
+            "12345"
+        );
+        assert_eq!(second.bill_to().email().as_deref(), Some("bob@example.com"));
+        assert!(second.ship_to().is_none());
+        assert!(second.line_items()[0].gtin().is_some());
+
+        let first = &invoices[1];
+        assert_eq!(&first.id(), "1");
+        assert_eq!(first.ship_to().as_ref().unwrap().name(), "Alice's Shop");
+        assert!(first.sender().address().is_none());
+    }
+
+    #[test]
+    fn test_terms_column() {
+        let data = "invoice_id,created_datetime,terms,sender_name,bill_to_name,sku,title,quantity,price\n\
+                    1,2026-02-10T12:00:00+00:00,2/10 Net 30,S,B,A,Widget,1,100\n\
+                    2,2026-02-10T12:00:00+00:00,Net thirty,S,B,A,Widget,1,100";
+        let e = read_invoices(data.as_bytes()).unwrap_err();
+        assert!(e.to_string().contains("row 3, column 'terms'"), "{e}");
+
+        let invoices = read_invoices(
+            data.lines()
+                .take(2)
+                .collect::<Vec<_>>()
+                .join("\n")
+                .as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(
+            invoices[0].net_due_datetime().to_rfc3339(),
+            "2026-03-12T12:00:00+00:00"
+        );
+        assert_eq!(invoices[0].discount_amount().unwrap().to_string(), "2.00");
+    }
+
+    #[test]
+    fn test_reports_row_and_column() {
+        let e = read(&[
+            "1,,,,Sender,,,,,Bob,,,A,Widget,2,9.99,",
+            "1,,,,Sender,,,,,Bob,,,B,Gadget,two,9.99,",
+        ])
+        .unwrap_err();
+        assert!(e.to_string().contains("row 3, column 'quantity'"), "{e}");
+
+        let e = read(&["1,,,,Sender,,,,,Bob,,,A,Widget,2,,"]).unwrap_err();
+        assert!(e.to_string().contains("row 2, column 'price'"), "{e}");
+
+        let e = read(&["1,,,,Sender,1 St,,,,Bob,,,A,Widget,2,1,"]).unwrap_err();
+        assert!(e.to_string().contains("row 2, column 'sender_city'"), "{e}");
+
+        let e = read(&["1,yesterday,,,Sender,,,,,Bob,,,A,Widget,2,1,"]).unwrap_err();
+        assert!(
+            e.to_string().contains("row 2, column 'created_datetime'"),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn test_conflicting_invoice_values() {
+        let e = read(&[
+            "1,,,,Sender,,,,,Bob,,,A,Widget,2,9.99,",
+            "1,,,,Other Sender,,,,,Bob,,,B,Gadget,1,9.99,",
+        ])
+        .unwrap_err();
+        assert!(e.to_string().contains("row 3, column 'sender_name'"), "{e}");
+        assert!(e.to_string().contains("from row 2"), "{e}");
+    }
+
+    #[test]
+    fn test_bad_header() {
+        let e = read_invoices(
+            "invoice_id,sender_name,bill_to_name,sku,title,quantity,price,colour\n".as_bytes(),
+        )
+        .unwrap_err();
+        assert!(
+            e.to_string().contains("column 'colour': unknown column"),
+            "{e}"
+        );
+
+        let e = read_invoices("invoice_id,sender_name,sku,title,quantity,price\n".as_bytes())
+            .unwrap_err();
+        assert!(
+            e.to_string()
+                .contains("missing required column 'bill_to_name'"),
+            "{e}"
+        );
+    }
+}
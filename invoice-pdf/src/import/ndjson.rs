@@ -0,0 +1,150 @@
+//! Read invoices from newline delimited JSON, one invoice per line.
+//!
+//! Unlike a single JSON array, the input is parsed lazily so arbitrarily large batches can be
+//! processed with only one invoice in memory at a time. A line that fails to parse only affects
+//! that invoice, and the error names the line number so the rest of the batch can still be
+//! printed. Blank lines are skipped. A line that is not valid UTF-8 is reported like a line that
+//! fails to parse, while any other read error is reported once and ends the stream.
+
+use std::{io::BufRead, marker::PhantomData};
+
+use serde::de::DeserializeOwned;
+
+use crate::{Invoice, error::AddContext};
+
+/// Iterator over the invoices of an NDJSON stream
+///
+/// Each item is the 1-based line number the invoice was read from along with the parsed
+/// [`Invoice`] or the error that prevented parsing it. [`NdjsonReader::values`] yields each line
+/// as a [`serde_json::Value`] instead, for callers that adjust invoices before deserializing them.
+///
+/// # Example
+/// ```rust
+/// use invoice_pdf::import::ndjson::NdjsonReader;
+///
+/// let data = "{\"id\": \"1\"}\n\nnot json\n";
+/// let lines: Vec<_> = NdjsonReader::new(data.as_bytes())
+///     .map(|(line, invoice)| (line, invoice.is_ok()))
+///     .collect();
+/// assert_eq!(lines, vec![(1, false), (3, false)]);
+/// ```
+pub struct NdjsonReader<R, T = Invoice> {
+    lines: std::io::Lines<R>,
+    line: usize,
+    stopped: bool,
+    item: PhantomData<T>,
+}
+
+impl<R: BufRead> NdjsonReader<R> {
+    /// Create a reader that pulls invoices from `reader` one line at a time
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            line: 0,
+            stopped: false,
+            item: PhantomData,
+        }
+    }
+}
+
+impl<R: BufRead> NdjsonReader<R, serde_json::Value> {
+    /// Create a reader that pulls each line from `reader` as an unchecked JSON value
+    pub fn values(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            line: 0,
+            stopped: false,
+            item: PhantomData,
+        }
+    }
+}
+
+impl<R: BufRead, T: DeserializeOwned> Iterator for NdjsonReader<R, T> {
+    type Item = (usize, Result<T, crate::Error>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.stopped {
+            return None;
+        }
+        loop {
+            let raw = self.lines.next()?;
+            self.line += 1;
+            let context = format!("reading invoice from line {}", self.line);
+            let raw = match raw {
+                Ok(raw) => raw,
+                Err(e) => {
+                    // Only a badly encoded line can be skipped, other errors such as reading a
+                    // directory repeat on every call
+                    self.stopped = e.kind() != std::io::ErrorKind::InvalidData;
+                    let e = crate::Error::from(e).at_line(self.line);
+                    return Some((self.line, Err(e).add_context(&context)));
+                }
+            };
+            if raw.trim().is_empty() {
+                continue;
+            }
+            let invoice = serde_json::from_str(&raw)
+                .map_err(|e| {
+                    let column = e.column();
+                    crate::Error::from(e).at_line(self.line).at_column(column)
+                })
+                .add_context(&context);
+            return Some((self.line, invoice));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INVOICE: &str = r#"{"id":"ID","created_datetime":"2026-02-10T12:00:00+00:00","net_due_datetime":"2026-02-10T12:00:00+00:00","bill_to":{"name":"B"},"sender":{"name":"S"},"line_items":[{"sku":"A","title":"T","quantity":"2","price":"1.50"}],"paid":"0","post_scripts":[]}"#;
+
+    #[test]
+    fn test_reads_each_line() {
+        let data = format!(
+            "{}\n\n{}\n{{\"id\": \"broken\"\n{}",
+            INVOICE.replace("ID", "1"),
+            INVOICE.replace("ID", "2"),
+            INVOICE.replace("ID", "4")
+        );
+        let results: Vec<_> = NdjsonReader::new(data.as_bytes()).collect();
+        assert_eq!(results.len(), 4);
+
+        let (line, invoice) = &results[0];
+        assert_eq!(*line, 1);
+        assert_eq!(&invoice.as_ref().unwrap().total().to_string(), "3.00");
+        assert_eq!(results[1].0, 3);
+        assert_eq!(&results[1].1.as_ref().unwrap().id(), "2");
+
+        let (line, invoice) = &results[2];
+        assert_eq!(*line, 4);
+        let e = invoice.as_ref().unwrap_err();
+        assert!(e.to_string().contains("reading invoice from line 4"), "{e}");
+
+        assert_eq!(results[3].0, 5);
+        assert_eq!(&results[3].1.as_ref().unwrap().id(), "4");
+    }
+
+    #[test]
+    fn test_stops_after_read_error() {
+        struct Broken;
+
+        impl std::io::Read for Broken {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::from(std::io::ErrorKind::IsADirectory))
+            }
+        }
+
+        let results: Vec<_> = NdjsonReader::new(std::io::BufReader::new(Broken))
+            .take(5)
+            .collect();
+        assert_eq!(results.len(), 1);
+        assert!(results[0].1.is_err());
+
+        let data = b"\xff\n{\"id\": \"broken\"}\n";
+        let results: Vec<_> = NdjsonReader::new(&data[..]).collect();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].0, 2);
+    }
+}
@@ -0,0 +1,140 @@
+//! Read invoices from TOML documents.
+//!
+//! TOML documents cannot be a bare array, so invoices are given as an array of tables named
+//! `invoices` using the same field names as the JSON layout. Amounts may be written as strings or
+//! numbers and are rounded exactly like their JSON counterparts. Datetimes may be RFC 3339 strings
+//! or native TOML offset datetimes.
+//!
+//! ```toml
+//! [[invoices]]
+//! id = "1001"
+//! created_datetime = 2026-02-10T12:00:00-05:00
+//! net_due_datetime = 2026-03-10T12:00:00-05:00
+//! paid = 0
+//! post_scripts = []
+//! sender = { name = "Hometown Hardware" }
+//! bill_to = { name = "Smith Construction" }
+//!
+//! [[invoices.line_items]]
+//! sku = "2X4-8"
+//! title = "2x4x8 SPF Stud"
+//! quantity = 120
+//! price = "4.589"
+//! ```
+
+use std::io::Read;
+
+use crate::{Invoice, error::AddContext};
+
+/// Convert a TOML value into its JSON equivalent, writing datetimes as strings
+fn to_json(value: toml::Value) -> serde_json::Value {
+    match value {
+        toml::Value::String(s) => serde_json::Value::String(s),
+        toml::Value::Integer(i) => serde_json::Value::from(i),
+        toml::Value::Float(f) => serde_json::Value::from(f),
+        toml::Value::Boolean(b) => serde_json::Value::Bool(b),
+        toml::Value::Datetime(d) => serde_json::Value::String(d.to_string()),
+        toml::Value::Array(a) => serde_json::Value::Array(a.into_iter().map(to_json).collect()),
+        toml::Value::Table(t) => {
+            serde_json::Value::Object(t.into_iter().map(|(k, v)| (k, to_json(v))).collect())
+        }
+    }
+}
+
+/// Read every invoice from the `invoices` array of a TOML document
+///
+/// # Arguments
+/// - `reader` Source of the TOML document
+///
+/// # Returns
+/// - The invoices in the order they appear in the `invoices` array
+///
+/// # Errors
+/// - [`crate::Error`] if the document cannot be read, is not valid TOML, has no `invoices` array,
+///   or an entry does not match the invoice layout
+pub fn read_invoices<R: Read>(reader: R) -> Result<Vec<Invoice>, crate::Error> {
+    super::invoices_from_values(read_values(reader)?)
+}
+
+/// Read each entry of the `invoices` array in the JSON layout without deserializing it as an
+/// invoice
+///
+/// # Errors
+/// - [`crate::Error`] if the document cannot be read, is not valid TOML, or has no `invoices`
+///   array
+pub fn read_values<R: Read>(mut reader: R) -> Result<Vec<serde_json::Value>, crate::Error> {
+    let mut raw = String::new();
+    reader
+        .read_to_string(&mut raw)
+        .map_err(crate::Error::from)
+        .add_context("reading toml")?;
+    let mut document: toml::Table = toml::from_str(&raw)
+        .map_err(crate::Error::from)
+        .add_context("parsing toml")?;
+    let Some(toml::Value::Array(invoices)) = document.remove("invoices") else {
+        return Err(crate::Error::invalid(
+            "expected an array of tables named 'invoices'",
+        ))
+        .add_context("parsing toml");
+    };
+    Ok(invoices.into_iter().map(to_json).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_toml() {
+        let data = r#"
+[[invoices]]
+id = "1001"
+created_datetime = 2026-02-10T12:00:00-05:00
+net_due_datetime = "2026-03-10T12:00:00-05:00"
+paid = "1.005"
+post_scripts = []
+sender = { name = "Sender" }
+bill_to = { name = "Receiver" }
+
+[[invoices.line_items]]
+sku = "A"
+title = "Float price"
+quantity = 1.5
+price = 12.1291
+
+[[invoices.line_items]]
+sku = "B"
+title = "Integer price"
+quantity = 2
+price = 10
+
+[[invoices]]
+id = "1002"
+created_datetime = 2026-02-11T00:00:00Z
+net_due_datetime = 2026-02-11T00:00:00Z
+paid = 0
+post_scripts = []
+line_items = []
+sender = { name = "Sender" }
+bill_to = { name = "Other" }
+"#;
+        let invoices = read_invoices(data.as_bytes()).unwrap();
+        assert_eq!(invoices.len(), 2);
+        let inv = &invoices[0];
+        assert_eq!(
+            inv.created_datetime().to_rfc3339(),
+            "2026-02-10T12:00:00-05:00"
+        );
+        assert_eq!(&inv.line_items()[0].price().to_string(), "12.130");
+        assert_eq!(&inv.line_items()[0].quantity().to_string(), "1.50");
+        assert_eq!(&inv.paid().to_string(), "1.00");
+        assert_eq!(&inv.total().to_string(), "38.20");
+        assert_eq!(invoices[1].bill_to().name(), "Other");
+    }
+
+    #[test]
+    fn test_requires_invoices_array() {
+        let e = read_invoices("id = \"1\"".as_bytes()).unwrap_err();
+        assert!(e.to_string().contains("'invoices'"), "{e}");
+    }
+}
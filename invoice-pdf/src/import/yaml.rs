@@ -0,0 +1,109 @@
+//! Read invoices from YAML documents.
+//!
+//! The document must be a sequence of invoices using the same field names as the JSON layout.
+//! Amounts may be written as strings or numbers and are rounded exactly like their JSON
+//! counterparts. Datetimes are RFC 3339 strings.
+//!
+//! ```yaml
+//! - id: "1001"
+//!   created_datetime: 2026-02-10T12:00:00-05:00
+//!   net_due_datetime: 2026-03-10T12:00:00-05:00
+//!   paid: 0
+//!   post_scripts: []
+//!   sender:
+//!     name: Hometown Hardware
+//!   bill_to:
+//!     name: Smith Construction
+//!   line_items:
+//!     - sku: 2X4-8
+//!       title: 2x4x8 SPF Stud
+//!       quantity: 120
+//!       price: "4.589"
+//! ```
+
+use std::io::Read;
+
+use crate::{Invoice, error::AddContext};
+
+/// Read every invoice from a YAML sequence
+///
+/// # Arguments
+/// - `reader` Source of the YAML document
+///
+/// # Returns
+/// - The invoices in the order they appear in the sequence
+///
+/// # Errors
+/// - [`crate::Error`] if the document is not valid YAML, is not a sequence, or an entry does not
+///   match the invoice layout
+pub fn read_invoices<R: Read>(reader: R) -> Result<Vec<Invoice>, crate::Error> {
+    super::invoices_from_values(read_values(reader)?)
+}
+
+/// Read each entry of a YAML sequence in the JSON layout without deserializing it as an invoice
+///
+/// # Errors
+/// - [`crate::Error`] if the document is not valid YAML or is not a sequence
+pub fn read_values<R: Read>(reader: R) -> Result<Vec<serde_json::Value>, crate::Error> {
+    serde_yaml::from_reader(reader)
+        .map_err(crate::Error::from)
+        .add_context("parsing yaml")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_yaml() {
+        let data = r#"
+- id: "1001"
+  created_datetime: 2026-02-10T12:00:00-05:00
+  net_due_datetime: "2026-03-10T12:00:00-05:00"
+  sender:
+    name: Sender
+  bill_to:
+    name: Receiver
+    address:
+      line1: 1 Street St
+      city: City
+      province_code: PR
+      postal_code: "01234"
+  paid: 1.005
+  post_scripts: []
+  line_items:
+    - sku: A
+      title: Float price
+      quantity: 1.5
+      price: 12.1291
+    - sku: B
+      title: String price
+      quantity: "2"
+      price: "10"
+"#;
+        let invoices = read_invoices(data.as_bytes()).unwrap();
+        assert_eq!(invoices.len(), 1);
+        let inv = &invoices[0];
+        assert_eq!(&inv.id(), "1001");
+        assert_eq!(
+            inv.bill_to().address().as_ref().unwrap().postal_code(),
+            "01234"
+        );
+        assert_eq!(&inv.line_items()[0].price().to_string(), "12.130");
+        assert_eq!(&inv.line_items()[0].quantity().to_string(), "1.50");
+        assert_eq!(&inv.line_items()[1].price().to_string(), "10.000");
+        assert_eq!(&inv.paid().to_string(), "1.00");
+        assert_eq!(&inv.total().to_string(), "38.20");
+    }
+
+    #[test]
+    fn test_names_bad_invoice() {
+        let data = "- id: good\n  sender: {name: S}\n  bill_to: {name: B}\n  created_datetime: 2026-02-10T12:00:00Z\n  net_due_datetime: 2026-02-10T12:00:00Z\n  paid: 0\n  post_scripts: []\n  line_items: []\n- id: bad\n  sender: {name: S}\n";
+        let e = read_invoices(data.as_bytes()).unwrap_err();
+        assert!(
+            e.to_string()
+                .contains("deserializing invoice 1 with id 'bad'"),
+            "{e}"
+        );
+    }
+}
@@ -15,9 +15,14 @@ use derive_builder::Builder;
 use gtin::Gtin;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use crate::{error::AddContext, template_env::InvoiceTemplate};
-
-fn serialize_bigdecimal<S>(value: &BigDecimal, serializer: S) -> Result<S::Ok, S::Error>
+use crate::{
+    decimal::{Adjustment, RoundingPolicy, TotalRounding},
+    error::AddContext,
+    template_env::InvoiceTemplate,
+    terms::PaymentTerms,
+};
+
+pub(crate) fn serialize_bigdecimal<S>(value: &BigDecimal, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
@@ -31,50 +36,46 @@ where
     serializer.serialize_str(&value.to_rfc3339())
 }
 
-fn deserialize_scale3<'de, D>(deserializer: D) -> Result<BigDecimal, D::Error>
+fn serialize_optional_datetime<S>(
+    value: &Option<DateTime<FixedOffset>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
 where
-    D: Deserializer<'de>,
+    S: Serializer,
 {
-    let v = serde_json::Value::deserialize(deserializer)?;
-    let d = match v {
-        serde_json::Value::String(s) => {
-            BigDecimal::from_str(&s).map_err(serde::de::Error::custom)?
-        }
+    match value {
+        Some(value) => serialize_datetime(value, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Parse a decimal given in the JSON layout as a string or a number
+///
+/// Numbers with a fraction are converted through their 64 bit float, so they may not be exactly
+/// the decimal that was written.
+pub(crate) fn decimal_from_value(v: &serde_json::Value) -> Result<BigDecimal, String> {
+    match v {
+        serde_json::Value::String(s) => BigDecimal::from_str(s).map_err(|e| e.to_string()),
         serde_json::Value::Number(n) => {
             if let Some(i) = n.as_i64() {
-                BigDecimal::from(i)
+                Ok(BigDecimal::from(i))
             } else if let Some(f) = n.as_f64() {
-                BigDecimal::try_from(f).map_err(serde::de::Error::custom)?
+                BigDecimal::try_from(f).map_err(|e| e.to_string())
             } else {
-                return Err(serde::de::Error::custom("invalid number"));
+                Err(String::from("invalid number"))
             }
         }
-        _ => return Err(serde::de::Error::custom("expected string or number")),
-    };
-    Ok(scale3_from_bigdecimal(&d))
+        _ => Err(String::from("expected string or number")),
+    }
 }
 
-fn deserialize_scale2<'de, D>(deserializer: D) -> Result<BigDecimal, D::Error>
+/// Deserialize a decimal exactly as given, leaving rounding to the invoice's [`RoundingPolicy`]
+pub(crate) fn deserialize_decimal<'de, D>(deserializer: D) -> Result<BigDecimal, D::Error>
 where
     D: Deserializer<'de>,
 {
     let v = serde_json::Value::deserialize(deserializer)?;
-    let d = match v {
-        serde_json::Value::String(s) => {
-            BigDecimal::from_str(&s).map_err(serde::de::Error::custom)?
-        }
-        serde_json::Value::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                BigDecimal::from(i)
-            } else if let Some(f) = n.as_f64() {
-                BigDecimal::try_from(f).map_err(serde::de::Error::custom)?
-            } else {
-                return Err(serde::de::Error::custom("invalid number"));
-            }
-        }
-        _ => return Err(serde::de::Error::custom("expected string or number")),
-    };
-    Ok(scale2_from_bigdecimal(&d))
+    decimal_from_value(&v).map_err(serde::de::Error::custom)
 }
 
 fn deserialize_datetime<'de, D>(deserializer: D) -> Result<DateTime<FixedOffset>, D::Error>
@@ -85,16 +86,23 @@ where
     DateTime::parse_from_rfc3339(&s).map_err(serde::de::Error::custom)
 }
 
-fn scale3_from_bigdecimal(bd: &BigDecimal) -> BigDecimal {
-    bd.with_scale_round(3, bigdecimal::RoundingMode::Up)
-}
-
-fn scale2_from_bigdecimal(bd: &BigDecimal) -> BigDecimal {
-    bd.with_scale_round(2, bigdecimal::RoundingMode::HalfEven)
+fn deserialize_optional_datetime<'de, D>(
+    deserializer: D,
+) -> Result<Option<DateTime<FixedOffset>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| DateTime::parse_from_rfc3339(&s).map_err(serde::de::Error::custom))
+        .transpose()
 }
 
 /// A single invoice line item encoding information such as stock keeping unit, title, quantity,
 /// and unit price.
+///
+/// The quantity and price are kept exactly as given and rounded with the line's
+/// [`RoundingPolicy`] when read. That is the default policy for a line item on its own, and the
+/// invoice's policy once it is part of an [`Invoice`].
 #[derive(Debug, Builder, Serialize, Deserialize, Clone)]
 #[builder(setter(strip_option, into), pattern = "owned")]
 pub struct LineItem {
@@ -102,7 +110,7 @@ pub struct LineItem {
     title: String,
     #[serde(
         serialize_with = "serialize_bigdecimal",
-        deserialize_with = "deserialize_scale2"
+        deserialize_with = "deserialize_decimal"
     )]
     #[builder(setter(custom))]
     quantity: BigDecimal,
@@ -110,10 +118,17 @@ pub struct LineItem {
     gtin: Option<Gtin>,
     #[serde(
         serialize_with = "serialize_bigdecimal",
-        deserialize_with = "deserialize_scale3"
+        deserialize_with = "deserialize_decimal"
     )]
     #[builder(setter(custom))]
     price: BigDecimal,
+    #[serde(default)]
+    #[builder(default = Vec::new())]
+    remarks: Vec<String>,
+    /// Precision of the price, quantity and total. Taken from the invoice the line is part of
+    #[serde(skip)]
+    #[builder(setter(skip))]
+    rounding: RoundingPolicy,
 }
 
 /// A party involved in the invoice (sender or receiver)
@@ -141,23 +156,85 @@ pub struct Address {
     postal_code: String,
 }
 
-/// Invoice top level model
+/// A payment received against an invoice
 #[derive(Debug, Builder, Serialize, Deserialize, Clone)]
 #[builder(setter(strip_option, into), pattern = "owned")]
-pub struct Invoice {
-    id: String,
+pub struct Payment {
     #[serde(
         serialize_with = "serialize_datetime",
         deserialize_with = "deserialize_datetime"
     )]
     #[builder(default = Local::now().into())]
-    created_datetime: DateTime<FixedOffset>,
+    received_datetime: DateTime<FixedOffset>,
+    #[serde(
+        serialize_with = "serialize_bigdecimal",
+        deserialize_with = "deserialize_decimal"
+    )]
+    #[builder(setter(custom))]
+    amount: BigDecimal,
+    /// How the payment was made, such as check or card
+    #[builder(default)]
+    method: Option<String>,
+    /// A check number, transaction id or other reference to the payment
+    #[builder(default)]
+    reference: Option<String>,
+    /// Precision of the amount. Taken from the invoice the payment is part of
+    #[serde(skip)]
+    #[builder(setter(skip))]
+    rounding: RoundingPolicy,
+}
+
+/// The dates an invoice's [`PaymentTerms`] give, from its created date
+#[derive(Debug, Clone, Copy, Default)]
+struct TermsDates {
+    due: Option<DateTime<FixedOffset>>,
+    discount_deadline: Option<DateTime<FixedOffset>>,
+}
+
+/// Invoice top level model
+///
+/// Whether built or deserialized, the decimals of the invoice, its line items and its payments
+/// are kept as given and rounded once with the invoice's [`RoundingPolicy`] when read. An invoice
+/// without a due date of its own is due when its [`PaymentTerms`] say, see
+/// [`Invoice::net_due_datetime`].
+#[derive(Debug, Builder, Serialize, Deserialize, Clone)]
+#[builder(
+    setter(strip_option, into),
+    pattern = "owned",
+    build_fn(private, name = "build_exact")
+)]
+// The derived impls are wrapped by the `Serialize` and `Deserialize` impls below, which hand the
+// rounding policy to the line items and payments once all of the invoice's fields are known
+#[serde(remote = "Self")]
+pub struct Invoice {
+    id: String,
     #[serde(
         serialize_with = "serialize_datetime",
         deserialize_with = "deserialize_datetime"
     )]
     #[builder(default = Local::now().into())]
-    net_due_datetime: DateTime<FixedOffset>,
+    created_datetime: DateTime<FixedOffset>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_datetime",
+        deserialize_with = "deserialize_optional_datetime"
+    )]
+    #[builder(default)]
+    net_due_datetime: Option<DateTime<FixedOffset>>,
+    /// The date full payment is due, from `net_due_datetime` or else the terms. Worked out once
+    /// all of the other fields are known
+    #[serde(skip)]
+    #[builder(setter(skip))]
+    resolved_due_datetime: DateTime<FixedOffset>,
+    /// The dates the terms give for full payment and for the early-payment discount, worked out
+    /// along with `resolved_due_datetime`
+    #[serde(skip)]
+    #[builder(setter(skip))]
+    terms_dates: TermsDates,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[builder(default)]
+    terms: Option<PaymentTerms>,
     bill_to: Party,
     #[builder(default)]
     ship_to: Option<Party>,
@@ -166,12 +243,18 @@ pub struct Invoice {
     logo: Option<PathBuf>,
     #[builder(default = Vec::new())]
     line_items: Vec<LineItem>,
+    /// Amount paid that is not itemized in `payments`, such as a single total from a system
+    /// without a payment ledger
     #[serde(
+        default,
         serialize_with = "serialize_bigdecimal",
-        deserialize_with = "deserialize_scale2"
+        deserialize_with = "deserialize_decimal"
     )]
     #[builder(default = BigDecimal::from(0), setter(custom))]
     paid: BigDecimal,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[builder(default = Vec::new())]
+    payments: Vec<Payment>,
     #[builder(default)]
     acct_id: Option<String>,
     #[builder(default)]
@@ -180,37 +263,123 @@ pub struct Invoice {
     post_scripts: Vec<String>,
     #[builder(default)]
     payment_url: Option<String>,
+    /// Precision of every decimal on the invoice
+    #[serde(default, skip_serializing_if = "RoundingPolicy::is_default")]
+    #[builder(default)]
+    rounding: RoundingPolicy,
+    /// Set on reminders made by [`Invoice::overdue_reminder`], which are rendered with an
+    /// overdue notice as of this date
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_optional_datetime",
+        deserialize_with = "deserialize_optional_datetime"
+    )]
+    #[builder(default)]
+    overdue_as_of: Option<DateTime<FixedOffset>>,
+    /// Decimals that were rounded while reading the invoice
+    #[serde(skip)]
+    #[builder(setter(skip))]
+    rounding_adjustments: Vec<Adjustment>,
+}
+
+impl Serialize for Invoice {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        Invoice::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Invoice {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Invoice::deserialize(deserializer)?
+            .share_rounding()
+            .resolve_due_datetime()
+            .map_err(serde::de::Error::custom)
+    }
 }
 
 impl LineItemBuilder {
     pub fn price(self, p: impl Into<BigDecimal>) -> Self {
-        let p: BigDecimal = p.into();
-        let price = scale3_from_bigdecimal(&p);
         Self {
-            price: Some(price),
+            price: Some(p.into()),
             ..self
         }
     }
 
     pub fn quantity(self, q: impl Into<BigDecimal>) -> Self {
-        let q: BigDecimal = q.into();
-        let quantity = scale2_from_bigdecimal(&q);
         Self {
-            quantity: Some(quantity),
+            quantity: Some(q.into()),
             ..self
         }
     }
+
+    /// Add a single remark line below the line item's title
+    pub fn add_remark(self, remark: &str) -> Self {
+        match self.remarks {
+            Some(mut remarks) => {
+                remarks.push(remark.to_string());
+                Self {
+                    remarks: Some(remarks),
+                    ..self
+                }
+            }
+            None => Self {
+                remarks: Some(vec![remark.to_string()]),
+                ..self
+            },
+        }
+    }
+}
+
+impl PaymentBuilder {
+    /// Set the amount received
+    pub fn amount(self, a: impl Into<BigDecimal>) -> Self {
+        Self {
+            amount: Some(a.into()),
+            ..self
+        }
+    }
+}
+
+impl Payment {
+    /// Get the date and time the payment was received
+    pub fn received_datetime(&self) -> &DateTime<FixedOffset> {
+        &self.received_datetime
+    }
+
+    /// Get the amount received, rounded to the total precision of the invoice's rounding policy
+    pub fn amount(&self) -> BigDecimal {
+        self.rounding.total.round(&self.amount)
+    }
+
+    /// Get how the payment was made, if known
+    pub fn method(&self) -> &Option<String> {
+        &self.method
+    }
+
+    /// Get the reference to the payment, such as a check number, if one exists
+    pub fn reference(&self) -> &Option<String> {
+        &self.reference
+    }
 }
 
 impl LineItem {
-    /// Return the unit price for this line item.
+    /// Return the unit price for this line item, rounded to the price precision of its rounding
+    /// policy
     pub fn price(&self) -> BigDecimal {
-        self.price.clone()
+        self.rounding.price.round(&self.price)
     }
 
-    /// Return the quantity for this line item.
+    /// Return the quantity for this line item, rounded to the quantity precision of its rounding
+    /// policy
     pub fn quantity(&self) -> BigDecimal {
-        self.quantity.clone()
+        self.rounding.quantity.round(&self.quantity)
     }
 
     /// Return the title for this line item.
@@ -223,19 +392,36 @@ impl LineItem {
         self.sku.to_string()
     }
 
-    /// Return the computed total for this line item equal to `quantity * price`
+    /// Return the computed total for this line item equal to `quantity * price`, rounded to the
+    /// total precision of its rounding policy
     pub fn total(&self) -> BigDecimal {
-        (&self.price * &self.quantity).with_scale_round(2, bigdecimal::RoundingMode::HalfEven)
+        self.rounding.total.round(&self.extension())
+    }
+
+    /// Return the total for this line item before it is rounded, `quantity * price` of the
+    /// rounded quantity and price
+    pub fn extension(&self) -> BigDecimal {
+        self.price() * self.quantity()
+    }
+
+    /// The precision of this line item's decimals
+    pub fn rounding(&self) -> &RoundingPolicy {
+        &self.rounding
     }
 
     /// Return this line item's barcode/upc/gtin, if it exists
     pub fn gtin(&self) -> Option<Gtin> {
         self.gtin
     }
+
+    /// Any extra notes printed below the line item's title
+    pub fn remarks(&self) -> &Vec<String> {
+        &self.remarks
+    }
 }
 
 impl Invoice {
-    /// Compute net amount due as `sum(line_items) - paid`.
+    /// Compute net amount due as `total - paid`.
     ///
     /// # Returns
     /// A [`BigDecimal`] representing the remaining amount owed.
@@ -268,12 +454,15 @@ impl Invoice {
     /// assert_eq!(inv.net_due(), BigDecimal::from(0));
     /// ```
     pub fn net_due(&self) -> BigDecimal {
-        let line_item_total: BigDecimal = self.line_items.iter().map(LineItem::total).sum();
-        line_item_total - &self.paid
+        self.total() - self.paid()
     }
 
     /// Compute the invoice total as `sum(line_items)`
     ///
+    /// With [`TotalRounding::PerLine`], the default, this is the sum of the rounded line totals.
+    /// With [`TotalRounding::PerInvoice`] the unrounded line extensions are added up and the sum
+    /// is rounded once, so it may differ from the printed line totals by a few cents.
+    ///
     /// # Returns
     /// A [`BigDecimal`] representing the total value of the invoice without taking any payments
     /// into account
@@ -309,7 +498,47 @@ impl Invoice {
     ///
     /// ```
     pub fn total(&self) -> BigDecimal {
-        self.line_items.iter().map(LineItem::total).sum()
+        match self.rounding.totals {
+            TotalRounding::PerLine => self.line_items.iter().map(LineItem::total).sum(),
+            TotalRounding::PerInvoice => self.rounding.total.round(&self.unrounded_total()),
+        }
+    }
+
+    /// The sum of the unrounded line extensions
+    pub fn unrounded_total(&self) -> BigDecimal {
+        self.line_items.iter().map(LineItem::extension).sum()
+    }
+
+    /// How much rounding changed the total, as `total - unrounded_total`
+    ///
+    /// Which rounding that was is given by [`Self::total_rounding`].
+    pub fn rounding_difference(&self) -> BigDecimal {
+        self.total() - self.unrounded_total()
+    }
+
+    /// Whether the total was rounded line by line or once
+    pub fn total_rounding(&self) -> TotalRounding {
+        self.rounding.totals
+    }
+
+    /// Check that the logo can be read and the payment link drawn as a QR code
+    ///
+    /// [`Self::render_html`] quietly leaves either out when it fails, so call this first to
+    /// treat those problems as errors. [`crate::generate_pdf`] and the session pool call it
+    /// before printing, but their unchecked variants do not.
+    ///
+    /// # Errors
+    /// - [`crate::Error`] of kind [`crate::error::ErrorKind::InvalidLogo`] or
+    ///   [`crate::error::ErrorKind::QrCode`]
+    pub fn check_assets(&self) -> Result<(), crate::Error> {
+        let template = InvoiceTemplate { invoice: self };
+        template
+            .try_logo_data_uri()
+            .add_context(&format!("checking logo of invoice {}", self.id))?;
+        template
+            .try_payment_qrcode_data_uri()
+            .add_context(&format!("checking payment link of invoice {}", self.id))?;
+        Ok(())
     }
 
     /// Convert this invoice into an HTML string based on templates/base.html
@@ -320,6 +549,98 @@ impl Invoice {
             .add_context(&format!("rendering html for invoice {}", self.id))
     }
 
+    /// The precision of every decimal on the invoice
+    pub fn rounding(&self) -> &RoundingPolicy {
+        &self.rounding
+    }
+
+    /// Hand the invoice's rounding policy to its line items and payments, which round their
+    /// decimals with it when they are read
+    fn share_rounding(self) -> Self {
+        let rounding = self.rounding;
+        Self {
+            line_items: self
+                .line_items
+                .into_iter()
+                .map(|line| LineItem { rounding, ..line })
+                .collect(),
+            payments: self
+                .payments
+                .into_iter()
+                .map(|payment| Payment {
+                    rounding,
+                    ..payment
+                })
+                .collect(),
+            ..self
+        }
+    }
+
+    /// Work out the dates the terms give and the date full payment is due from the invoice's
+    /// own due date or its terms
+    ///
+    /// # Errors
+    /// - [`crate::Error`] if the terms' discount is not allowed or a date the terms give is past
+    ///   the last date that can be represented
+    fn resolve_due_datetime(self) -> Result<Self, crate::Error> {
+        let terms_dates = match &self.terms {
+            Some(terms) => {
+                // Terms put together from their public fields have not been checked yet
+                terms.check()?;
+                TermsDates {
+                    due: Some(terms.due_datetime(&self.created_datetime)?),
+                    discount_deadline: terms.discount_deadline(&self.created_datetime)?,
+                }
+            }
+            None => TermsDates::default(),
+        };
+        let resolved_due_datetime = self
+            .net_due_datetime
+            .or(terms_dates.due)
+            .unwrap_or(self.created_datetime);
+        Ok(Self {
+            resolved_due_datetime,
+            terms_dates,
+            ..self
+        })
+    }
+
+    /// Every decimal that was rounded to fit its field while reading the invoice
+    ///
+    /// Only recorded for invoices read with [`crate::defaults::InvoiceDefaults::invoice_from_value`]
+    /// in [`crate::decimal::DecimalMode::Lenient`] mode.
+    pub fn rounding_adjustments(&self) -> &[Adjustment] {
+        &self.rounding_adjustments
+    }
+
+    pub(crate) fn with_rounding_adjustments(self, rounding_adjustments: Vec<Adjustment>) -> Self {
+        Self {
+            rounding_adjustments,
+            ..self
+        }
+    }
+
+    /// The date an overdue reminder was made as of, if the invoice is one
+    pub fn overdue_as_of(&self) -> &Option<DateTime<FixedOffset>> {
+        &self.overdue_as_of
+    }
+
+    /// Add late charges as line items and mark the invoice as overdue as of `as_of`. The id gets
+    /// a `-reminder-YYYY-MM-DD` suffix so the reminder is not mistaken for the original
+    pub(crate) fn with_late_charges(
+        mut self,
+        charges: Vec<LineItem>,
+        as_of: DateTime<FixedOffset>,
+    ) -> Self {
+        self.line_items.extend(charges);
+        Self {
+            id: format!("{}-reminder-{}", self.id, as_of.format("%Y-%m-%d")),
+            overdue_as_of: Some(as_of),
+            ..self
+        }
+        .share_rounding()
+    }
+
     /// Return a copy of this [`Invoice`]'s id
     pub fn id(&self) -> String {
         self.id.to_string()
@@ -336,8 +657,45 @@ impl Invoice {
     }
 
     /// Get the date and time when full payment is due
+    ///
+    /// This is the invoice's own due date if it has one, or else the one its terms give. An
+    /// invoice with neither is due on receipt.
     pub fn net_due_datetime(&self) -> &DateTime<FixedOffset> {
-        &self.net_due_datetime
+        &self.resolved_due_datetime
+    }
+
+    /// Get the date and time the payment terms alone make the invoice due, if it has terms
+    ///
+    /// An invoice's own due date takes precedence, so use [`Self::net_due_datetime`] for the date
+    /// payment is actually due.
+    pub fn terms_due_datetime(&self) -> Option<&DateTime<FixedOffset>> {
+        self.terms_dates.due.as_ref()
+    }
+
+    /// Get the payment terms, if the invoice has any
+    pub fn terms(&self) -> &Option<PaymentTerms> {
+        &self.terms
+    }
+
+    /// The early-payment discount on the invoice total, rounded to the total precision, if the
+    /// terms offer one. An overdue reminder has missed the discount, so it has none
+    pub fn discount_amount(&self) -> Option<BigDecimal> {
+        if self.overdue_as_of.is_some() {
+            return None;
+        }
+        self.terms
+            .as_ref()?
+            .discount_amount(&self.total())
+            .map(|amount| self.rounding.total.round(&amount))
+    }
+
+    /// The last moment the early-payment discount may be taken, if the terms offer one and the
+    /// invoice is not an overdue reminder
+    pub fn discount_deadline(&self) -> Option<DateTime<FixedOffset>> {
+        if self.overdue_as_of.is_some() {
+            return None;
+        }
+        self.terms_dates.discount_deadline
     }
 
     /// Get the information for party being billed for the invoice. This may not be the same as
@@ -363,8 +721,24 @@ impl Invoice {
     }
 
     /// Get the amount paid on the invoice as a [`BigDecimal`]
+    ///
+    /// This is the sum of [`Self::payments`] and [`Self::unitemized_paid`].
     pub fn paid(&self) -> BigDecimal {
-        self.paid.clone()
+        self.payments
+            .iter()
+            .map(Payment::amount)
+            .fold(self.unitemized_paid(), |paid, amount| paid + amount)
+    }
+
+    /// Get the part of the amount paid that is not itemized in [`Self::payments`], rounded to the
+    /// total precision. This is the `paid` field of the JSON layout
+    pub fn unitemized_paid(&self) -> BigDecimal {
+        self.rounding.total.round(&self.paid)
+    }
+
+    /// Get the payments received against the invoice, in the order they were given
+    pub fn payments(&self) -> &Vec<Payment> {
+        &self.payments
     }
 
     /// Get the receiver's account id, if one exists
@@ -499,25 +873,80 @@ impl InvoiceBuilder {
         }
     }
 
-    /// Set the amount that has already been paid on the invoice
+    /// Set the amount that has already been paid on the invoice without itemizing it. Payments
+    /// added with [`Self::add_payment`] are paid on top of this
     pub fn paid(self, p: impl Into<BigDecimal>) -> Self {
-        let p: BigDecimal = p.into();
-        let price = scale2_from_bigdecimal(&p);
         Self {
-            paid: Some(price),
+            paid: Some(p.into()),
             ..self
         }
     }
+
+    /// Add a [`Payment`] to the invoice's payment history
+    pub fn add_payment(self, payment: Payment) -> Self {
+        match self.payments {
+            Some(mut payments) => {
+                payments.push(payment);
+                Self {
+                    payments: Some(payments),
+                    ..self
+                }
+            }
+            None => Self {
+                payments: Some(vec![payment]),
+                ..self
+            },
+        }
+    }
+
+    /// Builds a new `Invoice` whose decimals, and those of its line items and payments, are
+    /// rounded by its rounding policy
+    ///
+    /// # Errors
+    /// - If a required field has not been initialized
+    pub fn build(self) -> Result<Invoice, InvoiceBuilderError> {
+        self.build_exact()?
+            .share_rounding()
+            .resolve_due_datetime()
+            .map_err(|e| InvoiceBuilderError::ValidationError(e.to_string()))
+    }
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use bigdecimal::BigDecimal;
     use chrono::{DateTime, FixedOffset, TimeZone};
     use std::path::PathBuf;
     use std::str::FromStr;
 
+    /// A small valid invoice in the JSON layout with the fields in `overrides` replaced. A field
+    /// overridden with `null` is left out instead
+    pub(crate) fn invoice_json(overrides: serde_json::Value) -> serde_json::Value {
+        let mut invoice = serde_json::json!({
+            "id": "7",
+            "created_datetime": "2025-01-01T00:00:00Z",
+            "net_due_datetime": "2025-01-31T00:00:00Z",
+            "sender": {"name": "S"},
+            "bill_to": {"name": "B"},
+            "line_items": [{"sku": "A", "title": "T", "quantity": "2", "price": "5"}],
+            "post_scripts": [],
+        });
+        let fields = invoice.as_object_mut().unwrap();
+        for (key, value) in overrides.as_object().unwrap() {
+            match value {
+                serde_json::Value::Null => fields.remove(key),
+                value => fields.insert(key.clone(), value.clone()),
+            };
+        }
+        invoice
+    }
+
+    /// Deserialize [`invoice_json`] with the fields in `overrides` replaced
+    pub(crate) fn invoice_from_json(overrides: serde_json::Value) -> Invoice {
+        serde_json::from_value(invoice_json(overrides)).unwrap()
+    }
+
     fn make_line_item(qty: impl Into<BigDecimal>, price: &str) -> LineItem {
         LineItemBuilder::default()
             .sku("test")
@@ -533,61 +962,109 @@ mod tests {
         PartyBuilder::default().name(name).build().unwrap()
     }
 
+    /// Deserialize a line item with the given price and quantity, both on its own and as part
+    /// of an invoice with the default rounding policy
+    fn deserialize_line(price: &str, quantity: &str) -> Vec<serde_json::Result<LineItem>> {
+        let line =
+            serde_json::json!({"sku": "A", "title": "T", "quantity": quantity, "price": price});
+        let invoice = invoice_json(serde_json::json!({"line_items": [line]}));
+        vec![
+            serde_json::from_value::<LineItem>(line),
+            serde_json::from_value::<Invoice>(invoice).map(|i| i.line_items()[0].clone()),
+        ]
+    }
+
     #[test]
     fn test_deserialize_price() {
         #[derive(Deserialize)]
         struct Wrap {
-            #[serde(deserialize_with = "super::deserialize_scale3")]
+            #[serde(deserialize_with = "super::deserialize_decimal")]
             bd: BigDecimal,
         }
+        let round = |bd: &BigDecimal| RoundingPolicy::default().price.round(bd).to_string();
 
         let val = serde_json::json!({"bd": "12.121"});
         let w: Wrap = serde_json::from_value(val).unwrap();
-        assert_eq!(&w.bd.to_string(), "12.121");
+        assert_eq!(&round(&w.bd), "12.121");
 
         let val = serde_json::json!({"bd": "12.129"});
         let w: Wrap = serde_json::from_value(val).unwrap();
-        assert_eq!(&w.bd.to_string(), "12.129");
+        assert_eq!(&round(&w.bd), "12.129");
 
         let val = serde_json::json!({"bd": "12.1299"});
         let w: Wrap = serde_json::from_value(val).unwrap();
-        assert_eq!(&w.bd.to_string(), "12.130");
+        assert_eq!(&round(&w.bd), "12.130");
 
         let val = serde_json::json!({"bd": "12.1291"});
         let w: Wrap = serde_json::from_value(val).unwrap();
-        assert_eq!(&w.bd.to_string(), "12.130");
+        assert_eq!(&round(&w.bd), "12.130");
 
         let val = serde_json::json!({"bd": "12.1295"});
         let w: Wrap = serde_json::from_value(val).unwrap();
-        assert_eq!(&w.bd.to_string(), "12.130");
+        assert_eq!(&round(&w.bd), "12.130");
     }
 
     #[test]
     fn test_deserialize_scale2() {
         #[derive(Deserialize)]
         struct Wrap {
-            #[serde(deserialize_with = "super::deserialize_scale2")]
+            #[serde(deserialize_with = "super::deserialize_decimal")]
             bd: BigDecimal,
         }
+        let round = |bd: &BigDecimal| RoundingPolicy::default().total.round(bd).to_string();
 
         let val = serde_json::json!({"bd": "12.50"});
         let w: Wrap = serde_json::from_value(val).unwrap();
-        assert_eq!(&w.bd.to_string(), "12.50");
+        assert_eq!(&round(&w.bd), "12.50");
         let val = serde_json::json!({"bd": "reee"});
         let x = serde_json::from_value::<Wrap>(val);
         assert!(x.is_err());
 
         let val = serde_json::json!({"bd": "1.995"});
         let w: Wrap = serde_json::from_value(val).unwrap();
-        assert_eq!(&w.bd.to_string(), "2.00");
+        assert_eq!(&round(&w.bd), "2.00");
 
         let val = serde_json::json!({"bd": "-1.995"});
         let w: Wrap = serde_json::from_value(val).unwrap();
-        assert_eq!(&w.bd.to_string(), "-2.00");
+        assert_eq!(&round(&w.bd), "-2.00");
 
         let val = serde_json::json!({"bd": "5.001"});
         let w: Wrap = serde_json::from_value(val).unwrap();
-        assert_eq!(&w.bd.to_string(), "5.00");
+        assert_eq!(&round(&w.bd), "5.00");
+    }
+
+    #[test]
+    fn test_deserialize_line_price() {
+        for (price, expected) in [
+            ("12.121", "12.121"),
+            ("12.129", "12.129"),
+            ("12.1299", "12.130"),
+            ("12.1291", "12.130"),
+            ("12.1295", "12.130"),
+        ] {
+            for line in deserialize_line(price, "1") {
+                assert_eq!(&line.unwrap().price().to_string(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_deserialize_line_quantity_and_paid() {
+        for (quantity, expected) in [
+            ("12.50", "12.50"),
+            ("1.995", "2.00"),
+            ("-1.995", "-2.00"),
+            ("5.001", "5.00"),
+        ] {
+            for line in deserialize_line("1", quantity) {
+                assert_eq!(&line.unwrap().quantity().to_string(), expected);
+            }
+            let invoice = invoice_from_json(serde_json::json!({"paid": quantity}));
+            assert_eq!(&invoice.paid().to_string(), expected);
+        }
+        for line in deserialize_line("1", "reee") {
+            assert!(line.is_err());
+        }
     }
 
     #[test]
@@ -595,6 +1072,7 @@ mod tests {
         #[derive(Deserialize)]
         struct Wrap {
             #[serde(deserialize_with = "super::deserialize_datetime")]
+            #[allow(dead_code)]
             date: DateTime<FixedOffset>,
         }
 
@@ -627,7 +1105,7 @@ mod tests {
         }
 
         let dt = chrono::Utc
-            .with_ymd_and_hms(2026, 02, 09, 12, 00, 00)
+            .with_ymd_and_hms(2026, 2, 9, 12, 0, 0)
             .unwrap()
             .into();
         let j = serde_json::to_value(Wrap { dt }).unwrap();
@@ -806,7 +1284,7 @@ mod tests {
         // timezone by default
         let expected_date = chrono::Local::now();
         let created = inv.created_datetime;
-        let due = inv.net_due_datetime;
+        let due = inv.net_due_datetime();
         assert_eq!(expected_date.date_naive(), created.date_naive());
         assert_eq!(expected_date.date_naive(), due.date_naive());
 
@@ -862,7 +1340,7 @@ mod tests {
             make_line_item(3, "-18.4441"),
             make_line_item(BigDecimal::try_from(3.75).unwrap(), "10.03"),
         ];
-        let expected = vec![
+        let expected = [
             ("9.123", "9.12"),
             ("9.124", "1532.83"),
             ("10.000", "220.00"),
@@ -891,4 +1369,307 @@ mod tests {
         assert_eq!(&invoice.total().to_string(), "1774.22");
         assert_eq!(&invoice.net_due().to_string(), "1757.22");
     }
+
+    #[test]
+    fn test_invoice_math_per_invoice_rounding() {
+        let line_items = vec![
+            make_line_item(1, "9.123"),
+            make_line_item(168, "9.1231"),
+            make_line_item(22, "10"),
+            make_line_item(3, "10.001"),
+            make_line_item(3, "-18.4441"),
+            make_line_item(BigDecimal::try_from(3.75).unwrap(), "10.03"),
+        ];
+        let expected = [
+            ("9.123", "9.123"),
+            ("9.124", "1532.83200"),
+            ("10.000", "220.00000"),
+            ("10.001", "30.00300"),
+            ("-18.445", "-55.33500"),
+            ("10.030", "37.61250"),
+        ];
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|(a, b)| (a.to_string(), b.to_string()))
+            .collect();
+        let actual: Vec<_> = line_items
+            .iter()
+            .map(|l| (l.price().to_string(), l.extension().to_string()))
+            .collect();
+        assert_eq!(expected, actual);
+
+        let invoice = |totals| {
+            InvoiceBuilder::default()
+                .line_items(line_items.clone())
+                .sender(make_party("sender"))
+                .bill_to(make_party("receiver"))
+                .id("1")
+                .paid("16.999".parse::<BigDecimal>().unwrap())
+                .rounding(RoundingPolicy {
+                    totals,
+                    ..RoundingPolicy::default()
+                })
+                .build()
+                .unwrap()
+        };
+
+        let per_line = invoice(TotalRounding::PerLine);
+        assert_eq!(per_line.total_rounding(), TotalRounding::PerLine);
+        assert_eq!(&per_line.unrounded_total().to_string(), "1774.23550");
+        assert_eq!(&per_line.total().to_string(), "1774.22");
+        assert_eq!(&per_line.net_due().to_string(), "1757.22");
+        assert_eq!(&per_line.rounding_difference().to_string(), "-0.01550");
+
+        let per_invoice = invoice(TotalRounding::PerInvoice);
+        assert_eq!(per_invoice.total_rounding(), TotalRounding::PerInvoice);
+        assert_eq!(&per_invoice.paid().to_string(), "17.00");
+        assert_eq!(&per_invoice.total().to_string(), "1774.24");
+        assert_eq!(&per_invoice.net_due().to_string(), "1757.24");
+        assert_eq!(&per_invoice.rounding_difference().to_string(), "0.00450");
+        // The printed line totals are still rounded one by one
+        let line_totals: BigDecimal = per_invoice.line_items().iter().map(LineItem::total).sum();
+        assert_eq!(&line_totals.to_string(), "1774.22");
+    }
+
+    #[test]
+    fn test_rounding_policy() {
+        use crate::decimal::{Precision, RoundingMode};
+
+        let invoice: Invoice = serde_json::from_value(serde_json::json!({
+            "id": "1",
+            "created_datetime": "2026-02-10T12:00:00+00:00",
+            "net_due_datetime": "2026-02-10T12:00:00+00:00",
+            "bill_to": {"name": "B"},
+            "sender": {"name": "S"},
+            "line_items": [{"sku": "A", "title": "T", "quantity": "1.2345", "price": "2.12345"}],
+            "paid": "1.005",
+            "post_scripts": [],
+            "rounding": {
+                "price": {"places": 4, "mode": "half_up"},
+                "quantity": {"places": 3, "mode": "half_up"},
+                "total": {"places": 2, "mode": "half_up"},
+            },
+        }))
+        .unwrap();
+        let line = &invoice.line_items()[0];
+        assert_eq!(line.price().to_string(), "2.1235");
+        assert_eq!(line.quantity().to_string(), "1.235");
+        assert_eq!(line.total().to_string(), "2.62");
+        assert_eq!(invoice.paid().to_string(), "1.01");
+        assert!(invoice.render_html().unwrap().contains("$2.1235"));
+
+        // The policy survives a round trip, while the default one is left out
+        let value = serde_json::to_value(&invoice).unwrap();
+        assert_eq!(value["rounding"]["price"]["places"], 4);
+        let again: Invoice = serde_json::from_value(value).unwrap();
+        assert_eq!(again.rounding(), invoice.rounding());
+        let plain = InvoiceBuilder::default()
+            .id("2")
+            .sender(make_party("S"))
+            .bill_to(make_party("B"))
+            .build()
+            .unwrap();
+        assert!(
+            serde_json::to_value(&plain)
+                .unwrap()
+                .get("rounding")
+                .is_none()
+        );
+
+        let rounding = RoundingPolicy {
+            price: Precision::new(4, RoundingMode::HalfUp),
+            ..RoundingPolicy::default()
+        };
+        let invoice = InvoiceBuilder::default()
+            .id("3")
+            .sender(make_party("S"))
+            .bill_to(make_party("B"))
+            .rounding(rounding)
+            .add_line(
+                LineItemBuilder::default()
+                    .sku("A")
+                    .title("T")
+                    .quantity(3)
+                    .price("0.33335".parse::<BigDecimal>().unwrap())
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+        assert_eq!(invoice.line_items()[0].price().to_string(), "0.3334");
+        assert_eq!(invoice.total().to_string(), "1.00");
+    }
+
+    #[test]
+    fn test_payment_terms() {
+        let invoice = |terms: &str, due: Option<&str>| {
+            invoice_from_json(serde_json::json!({
+                "created_datetime": "2026-01-20T12:00:00-05:00",
+                "net_due_datetime": due,
+                "line_items": [{"sku": "A", "title": "T", "quantity": "3", "price": "33.33"}],
+                "terms": terms,
+            }))
+        };
+
+        let net = invoice("Net 30", None);
+        assert_eq!(
+            net.net_due_datetime().to_rfc3339(),
+            "2026-02-19T12:00:00-05:00"
+        );
+        assert_eq!(net.discount_amount(), None);
+        assert_eq!(net.discount_deadline(), None);
+        // The derived due date is left out so the terms still decide it after a round trip
+        let value = serde_json::to_value(&net).unwrap();
+        assert_eq!(value["terms"], "Net 30");
+        assert!(value.get("net_due_datetime").is_none());
+
+        let discounted = invoice("2/10 Net 30", None);
+        assert_eq!(discounted.discount_amount().unwrap().to_string(), "2.00");
+        assert_eq!(
+            discounted.discount_deadline().unwrap().to_rfc3339(),
+            "2026-01-30T12:00:00-05:00"
+        );
+
+        let explicit = invoice("EOM", Some("2026-02-01T12:00:00-05:00"));
+        assert_eq!(
+            explicit.net_due_datetime().to_rfc3339(),
+            "2026-02-01T12:00:00-05:00"
+        );
+        assert_eq!(
+            explicit.terms_due_datetime().unwrap().to_rfc3339(),
+            "2026-01-31T12:00:00-05:00"
+        );
+        assert_eq!(
+            invoice("EOM", None).net_due_datetime().to_rfc3339(),
+            "2026-01-31T12:00:00-05:00"
+        );
+
+        let err = InvoiceBuilder::default()
+            .id("3")
+            .sender(make_party("S"))
+            .bill_to(make_party("B"))
+            .created_datetime(DateTime::<chrono::Utc>::MAX_UTC.fixed_offset())
+            .terms(PaymentTerms::from_str("Net 30").unwrap())
+            .build()
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("past the last supported date"),
+            "{err}"
+        );
+
+        // Terms put together from their fields are checked when the invoice is built
+        let mut generous = PaymentTerms::from_str("2/10 Net 30").unwrap();
+        generous.discount.as_mut().unwrap().percent = BigDecimal::from(150);
+        let err = InvoiceBuilder::default()
+            .id("4")
+            .sender(make_party("S"))
+            .bill_to(make_party("B"))
+            .terms(generous)
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("not between 0 and 100"), "{err}");
+
+        let receipt = InvoiceBuilder::default()
+            .id("2")
+            .sender(make_party("S"))
+            .bill_to(make_party("B"))
+            .build()
+            .unwrap();
+        assert_eq!(receipt.net_due_datetime(), receipt.created_datetime());
+        assert!(receipt.terms().is_none());
+    }
+
+    #[test]
+    fn test_payment_ledger() {
+        let invoice = |mut overrides: serde_json::Value| {
+            overrides["line_items"] =
+                serde_json::json!([{"sku": "A", "title": "T", "quantity": "4", "price": "25"}]);
+            invoice_from_json(overrides)
+        };
+
+        // A single paid amount still works, and is written back out as given with no payments
+        let lump = invoice(serde_json::json!({"paid": "30"}));
+        assert_eq!(lump.paid().to_string(), "30.00");
+        assert!(lump.payments().is_empty());
+        let value = serde_json::to_value(&lump).unwrap();
+        assert_eq!(value["paid"], "30");
+        assert!(value.get("payments").is_none());
+
+        let ledger = invoice(serde_json::json!({
+            "payments": [
+                {
+                    "received_datetime": "2026-02-15T09:30:00-05:00",
+                    "amount": "20.005",
+                    "method": "check",
+                    "reference": "1042",
+                },
+                {"received_datetime": "2026-02-20T09:30:00-05:00", "amount": 15},
+            ],
+        }));
+        assert_eq!(ledger.unitemized_paid().to_string(), "0");
+        assert_eq!(ledger.paid().to_string(), "35.00");
+        assert_eq!(ledger.net_due().to_string(), "65.00");
+        let first = &ledger.payments()[0];
+        assert_eq!(first.amount().to_string(), "20.00");
+        assert_eq!(first.method().as_deref(), Some("check"));
+        assert_eq!(first.reference().as_deref(), Some("1042"));
+        assert_eq!(
+            first.received_datetime().to_rfc3339(),
+            "2026-02-15T09:30:00-05:00"
+        );
+        assert_eq!(ledger.payments()[1].method(), &None);
+
+        // Payments are paid on top of an unitemized amount
+        let both = invoice(serde_json::json!({
+            "paid": "10",
+            "payments": [{"received_datetime": "2026-02-15T09:30:00Z", "amount": "5"}],
+        }));
+        assert_eq!(both.paid().to_string(), "15.00");
+        let again: Invoice = serde_json::from_value(serde_json::to_value(&both).unwrap()).unwrap();
+        assert_eq!(again.paid(), both.paid());
+        assert_eq!(again.payments().len(), 1);
+
+        let built = InvoiceBuilder::default()
+            .id("2")
+            .sender(make_party("S"))
+            .bill_to(make_party("B"))
+            .add_payment(PaymentBuilder::default().amount(1).build().unwrap())
+            .add_payment(
+                PaymentBuilder::default()
+                    .amount("2.555".parse::<BigDecimal>().unwrap())
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+        assert_eq!(built.paid().to_string(), "3.56");
+        assert!(PaymentBuilder::default().build().is_err());
+    }
+
+    #[test]
+    fn test_structured_error_kinds() {
+        let e = crate::Error::from(InvoiceBuilder::default().id("1").build().unwrap_err());
+        assert!(matches!(e.kind, crate::error::ErrorKind::InvoiceBuilder(_)));
+
+        let e = crate::Error::from(serde_json::from_str::<Invoice>("{\n  \"id\": 1").unwrap_err());
+        let crate::error::ErrorKind::Json(json) = &e.kind else {
+            panic!("expected a JSON error, got {e}");
+        };
+        assert_eq!(json.line(), 2);
+
+        let invoice = InvoiceBuilder::default()
+            .id("1")
+            .sender(make_party("sender"))
+            .bill_to(make_party("receiver"))
+            .logo(PathBuf::from("./no-such-logo.png"))
+            .build()
+            .unwrap();
+        let e = invoice.check_assets().unwrap_err();
+        assert!(
+            matches!(&e.kind, crate::error::ErrorKind::InvalidLogo { path, .. } if path == &PathBuf::from("./no-such-logo.png"))
+        );
+        assert!(!e.kind.is_transient());
+        // Rendering still leaves the missing logo out rather than failing
+        assert!(invoice.render_html().is_ok());
+    }
 }
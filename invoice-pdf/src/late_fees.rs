@@ -0,0 +1,497 @@
+//! Late fees and interest on overdue invoices.
+//!
+//! An invoice is overdue once the calendar date it is due has passed and money is still owed on
+//! it. A [`LateFeePolicy`] lists the charges added to overdue invoices: flat fees, a percentage
+//! of the amount due, or simple interest on it for every day or month past due. Charges are only
+//! added once the invoice is more than [`LateFeePolicy::grace_days`] days past due.
+//!
+//! [`Invoice::overdue_reminder`] turns an overdue invoice into a reminder that itemizes each
+//! charge as a line item and is rendered with an overdue notice. Build reminders from the
+//! original invoice, since a reminder already includes its charges in the amount due.
+//!
+//! ```rust
+//! use invoice_pdf::{InvoiceBuilder, LineItemBuilder, PartyBuilder, late_fees::LateFeePolicy};
+//!
+//! let policy: LateFeePolicy = serde_json::from_value(serde_json::json!({
+//!     "fees": [
+//!         {"type": "flat", "amount": "25"},
+//!         {"type": "interest", "percent": "1.5", "per": "month"},
+//!     ],
+//! }))
+//! .unwrap();
+//! let created = chrono::DateTime::parse_from_rfc3339("2026-01-01T12:00:00Z").unwrap();
+//! let invoice = InvoiceBuilder::default()
+//!     .id("1")
+//!     .created_datetime(created)
+//!     .net_due_datetime(created)
+//!     .sender(PartyBuilder::default().name("S").build().unwrap())
+//!     .bill_to(PartyBuilder::default().name("B").build().unwrap())
+//!     .add_line(
+//!         LineItemBuilder::default()
+//!             .sku("A")
+//!             .title("Thing")
+//!             .quantity(1)
+//!             .price(100)
+//!             .build()
+//!             .unwrap(),
+//!     )
+//!     .build()
+//!     .unwrap();
+//!
+//! let as_of = chrono::DateTime::parse_from_rfc3339("2026-03-15T12:00:00Z").unwrap();
+//! let reminder = invoice.overdue_reminder(&policy, &as_of).unwrap().unwrap();
+//! assert_eq!(reminder.line_items().len(), 3);
+//! assert_eq!(reminder.net_due().to_string(), "128.00");
+//! ```
+
+use std::fmt::Display;
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{DateTime, FixedOffset, Months};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{
+    Invoice, LineItem, LineItemBuilder,
+    error::AddContext,
+    invoice::{deserialize_decimal, serialize_bigdecimal},
+    template_env::filters::pretty_price_helper,
+};
+
+/// How often interest is charged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterestPeriod {
+    /// For every day past due
+    Day,
+    /// For every full month past due
+    Month,
+}
+
+impl Display for InterestPeriod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InterestPeriod::Day => write!(f, "day"),
+            InterestPeriod::Month => write!(f, "month"),
+        }
+    }
+}
+
+/// A decimal that is zero or more, since a negative fee or rate would credit the customer
+fn deserialize_non_negative_decimal<'de, D>(deserializer: D) -> Result<BigDecimal, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = deserialize_decimal(deserializer)?;
+    if value < BigDecimal::zero() {
+        return Err(serde::de::Error::custom(format!(
+            "{value} is negative, late fees must be zero or more"
+        )));
+    }
+    Ok(value)
+}
+
+/// A single charge added to overdue invoices. Amounts and percentages cannot be negative
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum LateFee {
+    /// A fixed amount
+    Flat {
+        #[serde(
+            serialize_with = "serialize_bigdecimal",
+            deserialize_with = "deserialize_non_negative_decimal"
+        )]
+        amount: BigDecimal,
+    },
+    /// A percentage of the amount due, such as 5 for 5%
+    Percent {
+        #[serde(
+            serialize_with = "serialize_bigdecimal",
+            deserialize_with = "deserialize_non_negative_decimal"
+        )]
+        percent: BigDecimal,
+    },
+    /// Simple interest on the amount due at `percent` for every period past due
+    Interest {
+        #[serde(
+            serialize_with = "serialize_bigdecimal",
+            deserialize_with = "deserialize_non_negative_decimal"
+        )]
+        percent: BigDecimal,
+        per: InterestPeriod,
+    },
+}
+
+/// The charges added to overdue invoices
+///
+/// Deserializes from a table such as the `[late_fees]` table of a config file:
+///
+/// ```toml
+/// grace_days = 5
+/// fees = [
+///     { type = "flat", amount = "25" },
+///     { type = "interest", percent = "1.5", per = "month" },
+/// ]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LateFeePolicy {
+    /// How many days past due an invoice may be before any charges are added
+    pub grace_days: u32,
+    /// The charges added, in order
+    pub fees: Vec<LateFee>,
+}
+
+/// A late fee or interest charged on an overdue invoice
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LateCharge {
+    /// The sku of the charge's line item, `LATE-FEE` or `INTEREST`
+    pub sku: String,
+    /// What the charge is for, such as `Interest at 1.5% per month for 2 months on $100.00`
+    pub description: String,
+    /// The amount charged, rounded to the invoice's total precision
+    pub amount: BigDecimal,
+}
+
+impl TryFrom<LateCharge> for LineItem {
+    type Error = crate::Error;
+
+    /// The line item itemizing the charge on a reminder
+    ///
+    /// # Errors
+    /// - [`crate::Error`] if the amount is negative, which a policy built in code rather than
+    ///   deserialized can produce
+    fn try_from(charge: LateCharge) -> Result<Self, Self::Error> {
+        if charge.amount < BigDecimal::zero() {
+            return Err(crate::Error::invalid(format!(
+                "late charge '{}' of {} is negative",
+                charge.description, charge.amount
+            )));
+        }
+        Ok(LineItemBuilder::default()
+            .sku(charge.sku)
+            .title(charge.description)
+            .quantity(1)
+            .price(charge.amount)
+            .build()?)
+    }
+}
+
+/// The number of full months from `from` to `to`, by calendar date
+fn months_between(from: &DateTime<FixedOffset>, to: &DateTime<FixedOffset>) -> u32 {
+    let (from, to) = (from.date_naive(), to.date_naive());
+    let mut months = 0;
+    while from
+        .checked_add_months(Months::new(months + 1))
+        .is_some_and(|next| next <= to)
+    {
+        months += 1;
+    }
+    months
+}
+
+impl Invoice {
+    /// How many calendar days past its due date the invoice is on the date of `as_of`, or zero
+    /// or less if it is not yet past due. Each date is taken in its own UTC offset
+    pub fn days_overdue(&self, as_of: &DateTime<FixedOffset>) -> i64 {
+        (as_of.date_naive() - self.net_due_datetime().date_naive()).num_days()
+    }
+
+    /// Whether money is still owed on the invoice after the date it was due
+    pub fn is_overdue(&self, as_of: &DateTime<FixedOffset>) -> bool {
+        self.days_overdue(as_of) > 0 && self.net_due() > BigDecimal::zero()
+    }
+
+    /// The charges `policy` adds to the invoice as of `as_of`, based on its [`Self::net_due`]
+    ///
+    /// Empty unless the invoice is overdue by more than the policy's grace days. Charges that
+    /// come to zero, such as monthly interest in the first month, are left out.
+    pub fn late_fees(
+        &self,
+        policy: &LateFeePolicy,
+        as_of: &DateTime<FixedOffset>,
+    ) -> Vec<LateCharge> {
+        if !self.is_overdue(as_of) || self.days_overdue(as_of) <= i64::from(policy.grace_days) {
+            return Vec::new();
+        }
+        let due = self.net_due();
+        let total = self.rounding().total;
+        // The amount the fees are charged on, formatted the way the template prints prices
+        let due_text = pretty_price_helper(due.clone(), total.places as usize);
+        let percent_of = |percent: &BigDecimal| &due * percent / BigDecimal::from(100);
+        policy
+            .fees
+            .iter()
+            .filter_map(|fee| {
+                let (sku, description, amount) = match fee {
+                    LateFee::Flat { amount } => {
+                        ("LATE-FEE", String::from("Late fee"), amount.clone())
+                    }
+                    LateFee::Percent { percent } => (
+                        "LATE-FEE",
+                        format!("Late fee of {}% on {due_text}", percent.normalized()),
+                        percent_of(percent),
+                    ),
+                    LateFee::Interest { percent, per } => {
+                        let periods = match per {
+                            InterestPeriod::Day => self.days_overdue(as_of),
+                            InterestPeriod::Month => {
+                                i64::from(months_between(self.net_due_datetime(), as_of))
+                            }
+                        };
+                        let plural = if periods == 1 { "" } else { "s" };
+                        (
+                            "INTEREST",
+                            format!(
+                                "Interest at {}% per {per} for {periods} {per}{plural} on \
+                                 {due_text}",
+                                percent.normalized()
+                            ),
+                            percent_of(percent) * BigDecimal::from(periods),
+                        )
+                    }
+                };
+                let amount = total.round(&amount);
+                (!amount.is_zero()).then(|| LateCharge {
+                    sku: String::from(sku),
+                    description,
+                    amount,
+                })
+            })
+            .collect()
+    }
+
+    /// Turn the invoice into a reminder that adds the charges of `policy` as line items and is
+    /// rendered with an overdue notice as of `as_of`. The reminder's id is the invoice's with a
+    /// `-reminder-YYYY-MM-DD` suffix, so writing it by id does not replace the original
+    ///
+    /// # Returns
+    /// - `None` if the invoice is not overdue as of `as_of`
+    ///
+    /// # Errors
+    /// - [`crate::Error`] if a charge cannot be turned into a line item, see
+    ///   [`LineItem::try_from`]
+    pub fn overdue_reminder(
+        &self,
+        policy: &LateFeePolicy,
+        as_of: &DateTime<FixedOffset>,
+    ) -> Result<Option<Invoice>, crate::Error> {
+        if !self.is_overdue(as_of) {
+            return Ok(None);
+        }
+        let charges = self
+            .late_fees(policy, as_of)
+            .into_iter()
+            .map(LineItem::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.with_invoice_id(self.id()))
+            .add_context("adding late charges to the reminder")?;
+        Ok(Some(self.clone().with_late_charges(charges, *as_of)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InvoiceBuilder, PartyBuilder};
+
+    fn datetime(s: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(s).unwrap()
+    }
+
+    fn invoice(price: &str, paid: &str) -> Invoice {
+        InvoiceBuilder::default()
+            .id("1")
+            .created_datetime(datetime("2026-01-01T12:00:00-05:00"))
+            .net_due_datetime(datetime("2026-01-31T12:00:00-05:00"))
+            .sender(PartyBuilder::default().name("S").build().unwrap())
+            .bill_to(PartyBuilder::default().name("B").build().unwrap())
+            .add_line(
+                LineItemBuilder::default()
+                    .sku("A")
+                    .title("T")
+                    .quantity(1)
+                    .price(price.parse::<BigDecimal>().unwrap())
+                    .build()
+                    .unwrap(),
+            )
+            .paid(paid.parse::<BigDecimal>().unwrap())
+            .build()
+            .unwrap()
+    }
+
+    fn policy(grace_days: u32) -> LateFeePolicy {
+        LateFeePolicy {
+            grace_days,
+            fees: vec![
+                LateFee::Flat {
+                    amount: BigDecimal::from(25),
+                },
+                LateFee::Percent {
+                    percent: BigDecimal::from(5),
+                },
+                LateFee::Interest {
+                    percent: "1.5".parse().unwrap(),
+                    per: InterestPeriod::Month,
+                },
+                LateFee::Interest {
+                    percent: "0.05".parse().unwrap(),
+                    per: InterestPeriod::Day,
+                },
+            ],
+        }
+    }
+
+    fn charges(charges: &[LateCharge]) -> Vec<(String, String)> {
+        charges
+            .iter()
+            .map(|c| (c.description.clone(), c.amount.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_overdue() {
+        let invoice = invoice("200", "50");
+        assert_eq!(invoice.days_overdue(&datetime("2026-01-31T23:00:00Z")), 0);
+        assert!(!invoice.is_overdue(&datetime("2026-01-31T23:59:00-05:00")));
+        assert!(invoice.is_overdue(&datetime("2026-02-01T00:00:00-05:00")));
+
+        let paid = self::invoice("200", "200");
+        assert!(!paid.is_overdue(&datetime("2026-06-01T00:00:00Z")));
+        assert!(
+            paid.overdue_reminder(&policy(0), &datetime("2026-06-01T00:00:00Z"))
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_late_fees() {
+        let invoice = invoice("200", "50");
+        let as_of = datetime("2026-03-15T00:00:00Z");
+        assert_eq!(invoice.days_overdue(&as_of), 43);
+        assert_eq!(
+            charges(&invoice.late_fees(&policy(0), &as_of)),
+            [
+                ("Late fee", "25.00"),
+                ("Late fee of 5% on $150.00", "7.50"),
+                ("Interest at 1.5% per month for 1 month on $150.00", "2.25"),
+                ("Interest at 0.05% per day for 43 days on $150.00", "3.22"),
+            ]
+            .map(|(d, a)| (d.to_string(), a.to_string()))
+        );
+
+        // Within the grace period nothing is charged, and monthly interest starts after a month
+        assert!(invoice.late_fees(&policy(43), &as_of).is_empty());
+        let early = datetime("2026-02-10T00:00:00Z");
+        assert_eq!(charges(&invoice.late_fees(&policy(5), &early)).len(), 3);
+    }
+
+    #[test]
+    fn test_overdue_reminder() {
+        let invoice = invoice("200", "50");
+        let as_of = datetime("2026-03-15T00:00:00Z");
+        let reminder = invoice
+            .overdue_reminder(&policy(0), &as_of)
+            .unwrap()
+            .unwrap();
+        assert_eq!(reminder.id(), "1-reminder-2026-03-15");
+        assert_eq!(reminder.overdue_as_of(), &Some(as_of));
+        assert_eq!(reminder.line_items().len(), 5);
+        assert_eq!(reminder.line_items()[3].sku(), "INTEREST");
+        assert_eq!(reminder.net_due().to_string(), "187.97");
+
+        let html = reminder.render_html().unwrap();
+        assert!(
+            html.contains("43 day(s) past due as of 2026-03-15"),
+            "{html}"
+        );
+        assert!(!invoice.render_html().unwrap().contains("past due"));
+
+        let again: Invoice =
+            serde_json::from_value(serde_json::to_value(&reminder).unwrap()).unwrap();
+        assert_eq!(again.overdue_as_of(), &Some(as_of));
+        assert_eq!(again.net_due(), reminder.net_due());
+    }
+
+    #[test]
+    fn test_overdue_reminder_drops_discount() {
+        let invoice = InvoiceBuilder::default()
+            .id("1")
+            .created_datetime(datetime("2026-01-01T12:00:00-05:00"))
+            .terms("2/10 Net 30".parse::<crate::terms::PaymentTerms>().unwrap())
+            .sender(PartyBuilder::default().name("S").build().unwrap())
+            .bill_to(PartyBuilder::default().name("B").build().unwrap())
+            .add_line(invoice("200", "0").line_items()[0].clone())
+            .build()
+            .unwrap();
+        assert!(invoice.discount_amount().is_some());
+        assert!(invoice.render_html().unwrap().contains("if paid by"));
+
+        let as_of = datetime("2026-03-15T00:00:00Z");
+        let reminder = invoice
+            .overdue_reminder(&policy(0), &as_of)
+            .unwrap()
+            .unwrap();
+        assert_eq!(reminder.discount_amount(), None);
+        assert_eq!(reminder.discount_deadline(), None);
+        assert!(!reminder.render_html().unwrap().contains("if paid by"));
+    }
+
+    #[test]
+    fn test_overdue_reminder_fails_on_charge_that_cannot_be_itemized() {
+        let policy = LateFeePolicy {
+            grace_days: 0,
+            fees: vec![
+                LateFee::Flat {
+                    amount: BigDecimal::from(25),
+                },
+                LateFee::Flat {
+                    amount: BigDecimal::from(-5),
+                },
+            ],
+        };
+        let e = invoice("200", "0")
+            .overdue_reminder(&policy, &datetime("2026-03-15T00:00:00Z"))
+            .unwrap_err();
+        assert_eq!(e.invoice_id(), Some("1"));
+        assert_eq!(
+            e.to_string(),
+            "adding late charges to the reminder -> late charge 'Late fee' of -5.00 is negative"
+        );
+    }
+
+    #[test]
+    fn test_policy_from_toml_like_values() {
+        let policy: LateFeePolicy = serde_json::from_value(serde_json::json!({
+            "grace_days": 10,
+            "fees": [
+                {"type": "flat", "amount": 25},
+                {"type": "interest", "percent": "1.5", "per": "day"},
+            ],
+        }))
+        .unwrap();
+        assert_eq!(policy.grace_days, 10);
+        assert_eq!(
+            policy.fees[1],
+            LateFee::Interest {
+                percent: "1.5".parse().unwrap(),
+                per: InterestPeriod::Day,
+            }
+        );
+        assert!(
+            serde_json::from_value::<LateFeePolicy>(
+                serde_json::json!({"fees": [{"type": "penalty"}]})
+            )
+            .is_err()
+        );
+        for fee in [
+            serde_json::json!({"type": "flat", "amount": "-25"}),
+            serde_json::json!({"type": "percent", "percent": -5}),
+            serde_json::json!({"type": "interest", "percent": "-1.5", "per": "month"}),
+        ] {
+            let e = serde_json::from_value::<LateFeePolicy>(serde_json::json!({"fees": [fee]}))
+                .unwrap_err();
+            assert!(e.to_string().contains("is negative"), "{e}");
+        }
+    }
+}
@@ -40,9 +40,21 @@
 //! generate_pdf(&inv);
 //! ```
 
+#[cfg(feature = "bundle")]
+pub mod bundle;
+pub mod customers;
+pub mod decimal;
+pub mod defaults;
 pub mod error;
+pub mod import;
 pub mod invoice;
+pub mod late_fees;
+pub mod page;
+pub mod pool;
+pub mod schema;
 pub mod template_env;
+pub mod terms;
+pub mod validation;
 
 use std::{
     net::TcpListener,
@@ -56,14 +68,18 @@ pub use error::Error;
 pub use invoice::{
     Address, AddressBuilder, AddressBuilderError, Invoice, InvoiceBuilder, InvoiceBuilderError,
     LineItem, LineItemBuilder, LineItemBuilderError, Party, PartyBuilder, PartyBuilderError,
+    Payment, PaymentBuilder, PaymentBuilderError,
 };
 
 use error::AddContext;
-use fantoccini::{
-    Client, ClientBuilder,
-    wd::{PrintConfigurationBuilder, PrintMargins, PrintSize},
-};
+use fantoccini::{Client, ClientBuilder, wd::PrintConfigurationBuilder};
+use page::PageSettings;
 use serde_json::Map;
+use validation::ValidationRules;
+
+fn chromedriver_launch_error(source: std::io::Error) -> crate::Error {
+    crate::Error::new(crate::error::ErrorKind::ChromedriverLaunch(source))
+}
 
 /// Starts ChromeDriver as a child process on port 4444
 ///
@@ -72,13 +88,11 @@ use serde_json::Map;
 ///
 /// # Errors
 /// - [`crate::Error`] if the chromedriver binary is not in the path, or if port 4444 is not
-/// available, or if the chromedriver process fails to start for any other reason
+///   available, or if the chromedriver process fails to start for any other reason
 pub fn start_chromedriver() -> Result<Child, crate::Error> {
     if is_port_in_use(4444) {
-        return Err(
-            crate::Error::from("Port 4444 is already in use".to_string())
-                .add_context("starting chromedriver"),
-        );
+        return Err(crate::Error::new(crate::error::ErrorKind::PortInUse(4444))
+            .add_context("starting chromedriver"));
     }
 
     let mut child = Command::new("chromedriver")
@@ -86,7 +100,7 @@ pub fn start_chromedriver() -> Result<Child, crate::Error> {
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .spawn()
-        .map_err(crate::Error::from)
+        .map_err(chromedriver_launch_error)
         .add_context("starting chromedriver on port 4444")?;
 
     for _ in 0..100 {
@@ -101,9 +115,9 @@ pub fn start_chromedriver() -> Result<Child, crate::Error> {
             .is_some()
         {
             return Err(
-                crate::Error::from(String::from("Chromedriver has stopped unexpectedly"))
+                crate::Error::new(crate::error::ErrorKind::ChromedriverExited)
                     .add_context("starting chromedriver"),
-            )?;
+            );
         }
 
         thread::sleep(Duration::from_millis(10));
@@ -118,14 +132,66 @@ pub fn start_chromedriver() -> Result<Child, crate::Error> {
             .add_context("killing chromedriver")
             .add_context("chromedriver failed to bind to port 4444")?;
         return Err(
-            crate::Error::from(String::from("Chromedriver failed to bind to port 4444"))
+            crate::Error::new(crate::error::ErrorKind::ChromedriverNotListening(4444))
                 .add_context("starting chromedriver"),
-        )?;
+        );
     }
 
     Ok(child)
 }
 
+/// Guard owning a running ChromeDriver process that stops it when dropped
+///
+/// Prefer this over calling [`start_chromedriver`] directly so that an early return or a panic
+/// cannot leave the chromedriver child process orphaned.
+pub struct ChromeDriver {
+    child: Child,
+}
+
+impl ChromeDriver {
+    /// Start ChromeDriver on port 4444 and take ownership of the process
+    ///
+    /// # Errors
+    /// - [`crate::Error`] under the same conditions as [`start_chromedriver`]
+    pub fn start() -> Result<Self, crate::Error> {
+        Ok(Self {
+            child: start_chromedriver()?,
+        })
+    }
+
+    /// Stop the ChromeDriver process and wait for it to exit, reporting any failure to do so
+    pub fn stop(mut self) -> Result<(), crate::Error> {
+        self.kill()
+    }
+
+    fn kill(&mut self) -> Result<(), crate::Error> {
+        if self
+            .child
+            .try_wait()
+            .map_err(crate::Error::from)
+            .add_context("checking chromedriver status")?
+            .is_some()
+        {
+            return Ok(());
+        }
+        self.child
+            .kill()
+            .map_err(crate::Error::from)
+            .add_context("killing chromedriver")?;
+        self.child
+            .wait()
+            .map_err(crate::Error::from)
+            .add_context("waiting for chromedriver to exit")?;
+        Ok(())
+    }
+}
+
+impl Drop for ChromeDriver {
+    fn drop(&mut self) {
+        let _ = self.kill();
+    }
+}
+
 /// Check if a given port is currently in use
 ///
 /// # Arguments
@@ -138,7 +204,10 @@ fn is_port_in_use(port: u16) -> bool {
     TcpListener::bind(format!("localhost:{port}")).is_err()
 }
 
-async fn connect_to_client() -> Result<Client, fantoccini::error::NewSessionError> {
+/// The address of the chromedriver started by [`start_chromedriver`]
+const WEBDRIVER_URL: &str = "http://localhost:4444";
+
+async fn connect_to_client(webdriver: &str) -> Result<Client, fantoccini::error::NewSessionError> {
     let mut caps = Map::new();
     caps.insert(
         "goog:chromeOptions".to_string(),
@@ -148,7 +217,7 @@ async fn connect_to_client() -> Result<Client, fantoccini::error::NewSessionErro
     );
     ClientBuilder::native()
         .capabilities(caps)
-        .connect("http://localhost:4444")
+        .connect(webdriver)
         .await
 }
 
@@ -158,6 +227,10 @@ async fn connect_to_client() -> Result<Client, fantoccini::error::NewSessionErro
 /// navigates a headless browser to the rendered HTML, prints the page as a PDF, and returns the
 /// resulting byte array
 ///
+/// The invoice is checked with [`Invoice::validate`] and [`Invoice::check_assets`] first, so an
+/// invoice that breaks a rule or has a missing logo fails instead of printing. Callers that have
+/// already checked it can print it with [`generate_pdf_unchecked`].
+///
 /// # Arguments
 ///
 /// - `invoice`: Reference to the [`Invoice`] to render and print.
@@ -169,6 +242,9 @@ async fn connect_to_client() -> Result<Client, fantoccini::error::NewSessionErro
 /// # Errors
 ///
 /// Returns `Err(crate::Error)` if any step fails:
+/// - checking the invoice with [`Invoice::validate`], if it breaks a rule with error severity
+/// - checking the invoice with [`Invoice::check_assets`], if its logo cannot be read or its
+///   payment link cannot be drawn as a QR code
 /// - connecting to the headless browser [`Client`]
 /// - setting up the templating environment
 /// - rendering the HTML template
@@ -211,13 +287,86 @@ async fn connect_to_client() -> Result<Client, fantoccini::error::NewSessionErro
 /// generate_pdf(&inv);
 /// ```
 pub async fn generate_pdf(invoice: &Invoice) -> Result<Vec<u8>, crate::Error> {
-    let client = connect_to_client()
+    generate_pdf_with_page(invoice, &PageSettings::default()).await
+}
+
+/// Generate a PDF byte array from [`Invoice`] printed with the given paper size and margins
+///
+/// # Errors
+/// - [`crate::Error`] under the same conditions as [`generate_pdf`]
+pub async fn generate_pdf_with_page(
+    invoice: &Invoice,
+    page: &PageSettings,
+) -> Result<Vec<u8>, crate::Error> {
+    generate_pdf_with_rules(invoice, page, &ValidationRules::default()).await
+}
+
+/// Generate a PDF byte array from [`Invoice`] printed with the given paper size and margins,
+/// after checking it with the rule severities in `rules`
+///
+/// Warnings are not reported. Call [`Invoice::validate_with`] first to see them, and then
+/// [`generate_pdf_unchecked`] to print without checking the invoice a second time.
+///
+/// # Errors
+/// - [`crate::Error`] under the same conditions as [`generate_pdf`]
+pub async fn generate_pdf_with_rules(
+    invoice: &Invoice,
+    page: &PageSettings,
+    rules: &ValidationRules,
+) -> Result<Vec<u8>, crate::Error> {
+    check_invoice(invoice, rules).add_context("generating pdf")?;
+    generate_pdf_unchecked(invoice, page).await
+}
+
+/// Generate a PDF byte array from [`Invoice`] printed with the given paper size and margins,
+/// without checking it first
+///
+/// For callers that have already checked the invoice with [`Invoice::validate_with`] and
+/// [`Invoice::check_assets`]. Like [`Invoice::render_html`], a logo that cannot be read or a
+/// payment link that cannot be drawn is left out of the PDF rather than failing.
+///
+/// # Errors
+/// - [`crate::Error`] under the same conditions as [`generate_pdf`], other than checking the
+///   invoice
+pub async fn generate_pdf_unchecked(
+    invoice: &Invoice,
+    page: &PageSettings,
+) -> Result<Vec<u8>, crate::Error> {
+    let client = connect_to_client(WEBDRIVER_URL)
         .await
         .map_err(crate::Error::from)
         .add_context("connecting to client")
         .add_context("generating pdf")?;
-    let render = invoice.render_html().add_context("generating pdf")?;
-    let encoded = general_purpose::STANDARD.encode(render.as_bytes());
+    let pdf = match invoice.render_html().add_context("generating pdf") {
+        Ok(render) => print_html(&client, &render, page).await,
+        Err(e) => Err(e),
+    };
+    // The session is closed whether or not printing worked so its browser does not outlive the
+    // call. Failing to close it does not make an already printed pdf any less valid.
+    let _ = client.close().await;
+    pdf
+}
+
+/// Fail if `invoice` breaks a rule that `rules` makes an error, or if its logo or payment QR code
+/// cannot be drawn
+fn check_invoice(invoice: &Invoice, rules: &ValidationRules) -> Result<(), crate::Error> {
+    invoice
+        .validate_with(rules)
+        .into_result()
+        .map_err(|e| e.with_invoice_id(invoice.id()))
+        .add_context(&format!("validating invoice {}", invoice.id()))?;
+    invoice
+        .check_assets()
+        .map_err(|e| e.with_invoice_id(invoice.id()))
+}
+
+/// Print already rendered invoice HTML to PDF using an open browser session
+async fn print_html(
+    client: &Client,
+    html: &str,
+    page: &PageSettings,
+) -> Result<Vec<u8>, crate::Error> {
+    let encoded = general_purpose::STANDARD.encode(html.as_bytes());
     let data_url = format!("data:text/html;base64,{encoded}");
     client
         .goto(&data_url)
@@ -225,16 +374,11 @@ pub async fn generate_pdf(invoice: &Invoice) -> Result<Vec<u8>, crate::Error> {
         .map_err(crate::Error::from)
         .add_context("navigating to address")
         .add_context("printing pdf")?;
-    Ok(client
+    client
         .print(
             PrintConfigurationBuilder::default()
-                .margins(PrintMargins {
-                    top: 0.5,
-                    left: 1.5,
-                    right: 1.5,
-                    bottom: 0.5,
-                })
-                .size(PrintSize::US_LETTER)
+                .margins(page.margins.into())
+                .size(page.size.into())
                 .build()
                 .map_err(crate::Error::from)
                 .add_context("configuring printer")
@@ -242,7 +386,7 @@ pub async fn generate_pdf(invoice: &Invoice) -> Result<Vec<u8>, crate::Error> {
         )
         .await
         .map_err(crate::Error::from)
-        .add_context("printing pdf")?)
+        .add_context("printing pdf")
 }
 
 #[cfg(test)]
@@ -253,6 +397,21 @@ mod tests {
 
     use super::*;
 
+    #[cfg(unix)]
+    #[test]
+    fn test_chromedriver_guard_kills_on_drop() {
+        let child = Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id().to_string();
+        drop(ChromeDriver { child });
+        let alive = Command::new("kill")
+            .args(["-0", &pid])
+            .stderr(Stdio::null())
+            .status()
+            .unwrap()
+            .success();
+        assert!(!alive);
+    }
+
     #[tokio::test]
     async fn test_generate_pdf() {
         let mut c = Command::new("chromedriver")
@@ -287,7 +446,8 @@ mod tests {
             .unwrap();
         let v = generate_pdf(&inv).await.unwrap();
         std::fs::write("test.pdf", &v).unwrap();
-        assert!(v.len() > 0);
+        assert!(!v.is_empty());
         c.kill().unwrap();
+        c.wait().unwrap();
     }
 }
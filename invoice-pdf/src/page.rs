@@ -0,0 +1,72 @@
+//! Paper size and margins used when printing invoices to PDF.
+
+use fantoccini::wd::{PrintMargins, PrintSize};
+use serde::{Deserialize, Serialize};
+
+/// The paper an invoice is printed on
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageSize {
+    /// US Letter, 8.5 by 11 inches
+    #[default]
+    Letter,
+    /// US Legal, 8.5 by 14 inches
+    Legal,
+    /// ISO A4, 210 by 297 millimeters
+    A4,
+    /// Any other size, in centimeters
+    Custom { width: f64, height: f64 },
+}
+
+impl From<PageSize> for PrintSize {
+    fn from(value: PageSize) -> Self {
+        let (width, height) = match value {
+            PageSize::Letter => (21.59, 27.94),
+            PageSize::Legal => (21.59, 35.56),
+            PageSize::A4 => (21.0, 29.7),
+            PageSize::Custom { width, height } => (width, height),
+        };
+        PrintSize { width, height }
+    }
+}
+
+/// Space left blank around each printed page, in centimeters
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Margins {
+    pub top: f64,
+    pub left: f64,
+    pub right: f64,
+    pub bottom: f64,
+}
+
+impl Default for Margins {
+    fn default() -> Self {
+        Self {
+            top: 0.5,
+            left: 1.5,
+            right: 1.5,
+            bottom: 0.5,
+        }
+    }
+}
+
+impl From<Margins> for PrintMargins {
+    fn from(value: Margins) -> Self {
+        PrintMargins {
+            top: value.top,
+            left: value.left,
+            right: value.right,
+            bottom: value.bottom,
+        }
+    }
+}
+
+/// Page layout for printing invoices. The default matches what [`crate::generate_pdf`] has
+/// always used: US Letter with half centimeter top and bottom margins
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PageSettings {
+    pub size: PageSize,
+    pub margins: Margins,
+}
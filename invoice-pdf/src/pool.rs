@@ -0,0 +1,197 @@
+//! A pool of reusable browser sessions for printing many invoices.
+//!
+//! [`crate::generate_pdf`] opens and closes a new browser session for every invoice, which is
+//! fine for one-off runs but slow for a long running service. [`SessionPool`] keeps idle sessions
+//! open between calls and caps how many are in use at once, so concurrent requests share a
+//! bounded number of browsers.
+
+use std::sync::Mutex;
+
+use fantoccini::Client;
+use tokio::sync::Semaphore;
+
+use crate::{
+    Invoice, WEBDRIVER_URL, check_invoice, connect_to_client, error::AddContext,
+    page::PageSettings, print_html, validation::ValidationRules,
+};
+
+/// A bounded set of browser sessions connected to the chromedriver on port 4444, or the
+/// webdriver given to [`SessionPool::with_webdriver_url`]
+///
+/// Sessions are opened lazily the first time they are needed. A session that fails while
+/// printing is closed rather than returned to the pool, so a crashed browser is replaced on the
+/// next call.
+pub struct SessionPool {
+    idle: Mutex<Vec<Client>>,
+    permits: Semaphore,
+    page: PageSettings,
+    rules: ValidationRules,
+    webdriver: String,
+}
+
+impl SessionPool {
+    /// Create a pool that keeps at most `size` sessions in use at once
+    ///
+    /// # Panics
+    /// - If `size` is 0
+    pub fn new(size: usize) -> Self {
+        assert!(
+            size > 0,
+            "a session pool needs room for at least one session"
+        );
+        Self {
+            idle: Mutex::new(Vec::new()),
+            permits: Semaphore::new(size),
+            page: PageSettings::default(),
+            rules: ValidationRules::default(),
+            webdriver: String::from(WEBDRIVER_URL),
+        }
+    }
+
+    /// Print with `page` instead of the default paper size and margins
+    pub fn with_page_settings(self, page: PageSettings) -> Self {
+        Self { page, ..self }
+    }
+
+    /// Check invoices with the rule severities in `rules` instead of the defaults
+    pub fn with_validation_rules(self, rules: ValidationRules) -> Self {
+        Self { rules, ..self }
+    }
+
+    /// Open sessions on the webdriver at `url`, such as `http://localhost:9515`, instead of the
+    /// chromedriver on port 4444
+    pub fn with_webdriver_url(self, url: impl Into<String>) -> Self {
+        Self {
+            webdriver: url.into(),
+            ..self
+        }
+    }
+
+    fn take_idle(&self) -> Option<Client> {
+        self.idle.lock().unwrap_or_else(|e| e.into_inner()).pop()
+    }
+
+    /// Generate a PDF byte array from [`Invoice`] using a pooled session, after checking it with
+    /// the pool's validation rules
+    ///
+    /// Waits for a free session if all of them are in use.
+    ///
+    /// # Errors
+    /// - [`crate::Error`] under the same conditions as [`crate::generate_pdf`]
+    pub async fn generate_pdf(&self, invoice: &Invoice) -> Result<Vec<u8>, crate::Error> {
+        check_invoice(invoice, &self.rules).add_context("generating pdf")?;
+        self.generate_pdf_unchecked(invoice).await
+    }
+
+    /// Generate a PDF byte array from [`Invoice`] using a pooled session, without checking it
+    /// first, for callers that already have. See [`crate::generate_pdf_unchecked`]
+    ///
+    /// # Errors
+    /// - [`crate::Error`] under the same conditions as [`crate::generate_pdf_unchecked`]
+    pub async fn generate_pdf_unchecked(&self, invoice: &Invoice) -> Result<Vec<u8>, crate::Error> {
+        let html = invoice.render_html().add_context("generating pdf")?;
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .map_err(|e| crate::Error::from(e.to_string()))
+            .add_context("waiting for a browser session")?;
+        let client = match self.take_idle() {
+            Some(client) => client,
+            None => connect_to_client(&self.webdriver)
+                .await
+                .map_err(crate::Error::from)
+                .add_context("connecting to client")
+                .add_context("generating pdf")?,
+        };
+
+        match print_html(&client, &html, &self.page).await {
+            Ok(pdf) => {
+                self.idle
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .push(client);
+                Ok(pdf)
+            }
+            Err(e) => {
+                let _ = client.close().await;
+                Err(e).add_context("generating pdf")
+            }
+        }
+    }
+
+    /// The number of open sessions waiting to be reused
+    pub fn idle_sessions(&self) -> usize {
+        self.idle.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Close every idle session
+    ///
+    /// Sessions in use by a concurrent call are returned to the pool afterwards and are not
+    /// closed.
+    pub async fn close(&self) {
+        let idle = std::mem::take(&mut *self.idle.lock().unwrap_or_else(|e| e.into_inner()));
+        for client in idle {
+            let _ = client.close().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{InvoiceBuilder, LineItemBuilder, PartyBuilder};
+
+    #[tokio::test]
+    async fn test_failed_connection_releases_session() {
+        let invoice = InvoiceBuilder::default()
+            .id("1")
+            .sender(PartyBuilder::default().name("S").build().unwrap())
+            .bill_to(PartyBuilder::default().name("B").build().unwrap())
+            .add_line(
+                LineItemBuilder::default()
+                    .sku("A")
+                    .title("T")
+                    .quantity(1)
+                    .price(1)
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+        // A port that was just bound and released refuses connections, so connecting fails.
+        // With a single session, the second call would wait forever if the first kept its permit.
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let pool = SessionPool::new(1).with_webdriver_url(format!("http://127.0.0.1:{port}"));
+        for _ in 0..2 {
+            let e = tokio::time::timeout(Duration::from_secs(30), pool.generate_pdf(&invoice))
+                .await
+                .expect("session was not released")
+                .unwrap_err();
+            assert!(e.to_string().contains("connecting to client"), "{e}");
+        }
+        pool.close().await;
+        assert_eq!(pool.idle_sessions(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_invoice_is_not_printed() {
+        let invoice = InvoiceBuilder::default()
+            .id("1")
+            .sender(PartyBuilder::default().name("S").build().unwrap())
+            .bill_to(PartyBuilder::default().name("B").build().unwrap())
+            .build()
+            .unwrap();
+        let pool = SessionPool::new(1);
+        let e = pool.generate_pdf(&invoice).await.unwrap_err();
+        assert_eq!(e.kind.name(), "validation");
+        assert_eq!(e.invoice_id(), Some("1"));
+        assert_eq!(pool.idle_sessions(), 0);
+    }
+}
@@ -0,0 +1,511 @@
+//! JSON Schema for the invoice input format.
+//!
+//! The schema describes the JSON layout accepted when reading an [`crate::Invoice`] through
+//! [`crate::defaults::InvoiceDefaults`], including the looser rules of the custom deserializers:
+//! decimals may be given as strings or numbers and datetimes must be RFC 3339 strings. The
+//! sender may be left to the defaults or picked with `sender_profile`, and `bill_to` and
+//! `ship_to` may be customer account ids, with `bill_to` left out when `acct_id` names the
+//! customer. Whether those are resolved depends on the defaults and customer directory in use.
+//! A copy is published in `schema/invoice.schema.json` so upstream systems can validate payloads
+//! before sending them.
+
+use serde_json::{Value, json};
+
+/// The `$id` used for the single invoice schema
+pub const SCHEMA_ID: &str =
+    "https://github.com/areif-dev/invoice-pdf-rs/invoice-pdf/schema/invoice.schema.json";
+
+/// Shared definitions referenced by both the invoice and batch schemas
+fn definitions() -> Value {
+    json!({
+        "decimal": {
+            "description": "A decimal number given as a string (preferred, exact) or a JSON number (converted through a 64 bit float). Extra decimal places are rounded away, or rejected along with numbers with a fraction in strict decimal mode",
+            "anyOf": [
+                {
+                    "type": "string",
+                    "pattern": "^[+-]?([0-9]+(\\.[0-9]*)?|\\.[0-9]+)([eE][+-]?[0-9]+)?$"
+                },
+                { "type": "number" }
+            ]
+        },
+        "datetime": {
+            "description": "An RFC 3339 datetime including its UTC offset, such as 2026-02-10T12:00:00-05:00",
+            "type": "string",
+            "format": "date-time"
+        },
+        "nullableString": {
+            "type": ["string", "null"]
+        },
+        "address": {
+            "description": "A postal address",
+            "type": "object",
+            "properties": {
+                "line1": { "type": "string" },
+                "line2": { "$ref": "#/$defs/nullableString" },
+                "city": { "type": "string" },
+                "province_code": { "description": "Province or state code, such as PA", "type": "string" },
+                "postal_code": { "type": "string" }
+            },
+            "required": ["line1", "city", "province_code", "postal_code"]
+        },
+        "party": {
+            "description": "A party involved in the invoice (sender or receiver)",
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "phone": { "$ref": "#/$defs/nullableString" },
+                "email": { "$ref": "#/$defs/nullableString" },
+                "address": {
+                    "anyOf": [{ "$ref": "#/$defs/address" }, { "type": "null" }]
+                }
+            },
+            "required": ["name"]
+        },
+        "precision": {
+            "description": "The decimal places a value keeps and how values with more are rounded",
+            "type": "object",
+            "properties": {
+                "places": { "type": "integer", "minimum": 0 },
+                "mode": {
+                    "enum": ["up", "down", "ceiling", "floor", "half_up", "half_down", "half_even"]
+                }
+            },
+            "required": ["places", "mode"],
+            "additionalProperties": false
+        },
+        "rounding": {
+            "description": "Precision of the invoice's decimals. Precisions left out keep their defaults",
+            "type": "object",
+            "properties": {
+                "price": { "$ref": "#/$defs/precision" },
+                "quantity": { "$ref": "#/$defs/precision" },
+                "total": {
+                    "description": "Precision of line totals, the invoice total and the amount paid",
+                    "$ref": "#/$defs/precision"
+                },
+                "totals": {
+                    "description": "Round each line total and add them up, or add up the unrounded line extensions and round once",
+                    "enum": ["per_line", "per_invoice"]
+                }
+            },
+            "required": [],
+            "additionalProperties": false
+        },
+        "lineItem": {
+            "description": "A single invoice line item",
+            "type": "object",
+            "properties": {
+                "sku": { "type": "string" },
+                "title": { "type": "string" },
+                "quantity": {
+                    "description": "Rounded with the invoice's quantity precision, by default half-even to 2 decimal places",
+                    "$ref": "#/$defs/decimal"
+                },
+                "gtin": {
+                    "description": "A GTIN-8, GTIN-12 (UPC), GTIN-13 or GTIN-14 barcode",
+                    "type": ["string", "integer", "null"]
+                },
+                "price": {
+                    "description": "Unit price, rounded with the invoice's price precision, by default up to 3 decimal places",
+                    "$ref": "#/$defs/decimal"
+                },
+                "remarks": {
+                    "type": "array",
+                    "items": { "type": "string" }
+                }
+            },
+            "required": ["sku", "title", "quantity", "price"]
+        },
+        "payment": {
+            "description": "A payment received against an invoice",
+            "type": "object",
+            "properties": {
+                "received_datetime": { "$ref": "#/$defs/datetime" },
+                "amount": {
+                    "description": "Rounded with the invoice's total precision",
+                    "$ref": "#/$defs/decimal"
+                },
+                "method": {
+                    "description": "How the payment was made, such as check or card",
+                    "$ref": "#/$defs/nullableString"
+                },
+                "reference": {
+                    "description": "A check number, transaction id or other reference to the payment",
+                    "$ref": "#/$defs/nullableString"
+                }
+            },
+            "required": ["received_datetime", "amount"]
+        },
+        "invoice": {
+            "description": "Invoice top level model. The sender may come from the defaults or a sender profile, and the bill-to party from the customer of acct_id",
+            "type": "object",
+            "properties": {
+                "id": { "type": "string" },
+                "created_datetime": { "$ref": "#/$defs/datetime" },
+                "net_due_datetime": {
+                    "description": "When full payment is due. Defaults to the date the terms give, or the created date without terms",
+                    "$ref": "#/$defs/datetime"
+                },
+                "terms": {
+                    "description": "Payment terms such as \"Net 30\", \"Due on receipt\", \"EOM\", \"Net 10 EOM\" or \"2/10 Net 30\", where 2/10 is a 2% discount for paying within 10 days",
+                    "type": "string"
+                },
+                "bill_to": {
+                    "description": "The party billed, or the account id of a customer in the customer directory. Defaults to the customer of acct_id",
+                    "oneOf": [{ "$ref": "#/$defs/party" }, { "type": "string" }]
+                },
+                "ship_to": {
+                    "description": "The party shipped to, or the account id of a customer in the customer directory",
+                    "anyOf": [
+                        { "$ref": "#/$defs/party" },
+                        { "type": "string" },
+                        { "type": "null" }
+                    ]
+                },
+                "sender": {
+                    "description": "Defaults to the sender of the sender profile or of the defaults",
+                    "$ref": "#/$defs/party"
+                },
+                "sender_profile": {
+                    "description": "Name of a sender profile whose sender, logo, payment link and post scripts are used in place of the defaults",
+                    "$ref": "#/$defs/nullableString"
+                },
+                "logo": {
+                    "description": "Path to a logo image on the machine doing the rendering",
+                    "$ref": "#/$defs/nullableString"
+                },
+                "line_items": {
+                    "type": "array",
+                    "items": { "$ref": "#/$defs/lineItem" }
+                },
+                "paid": {
+                    "description": "Amount already paid that is not itemized in payments, rounded with the invoice's total precision, by default half-even to 2 decimal places. Defaults to 0",
+                    "$ref": "#/$defs/decimal"
+                },
+                "payments": {
+                    "description": "Payments received, paid on top of paid",
+                    "type": "array",
+                    "items": { "$ref": "#/$defs/payment" }
+                },
+                "acct_id": { "$ref": "#/$defs/nullableString" },
+                "purchase_order": { "$ref": "#/$defs/nullableString" },
+                "post_scripts": {
+                    "type": "array",
+                    "items": { "type": "string" }
+                },
+                "payment_url": { "$ref": "#/$defs/nullableString" },
+                "rounding": { "$ref": "#/$defs/rounding" },
+                "overdue_as_of": {
+                    "description": "Set on overdue reminders, which are rendered with an overdue notice as of this date",
+                    "$ref": "#/$defs/datetime"
+                }
+            },
+            "required": ["id", "created_datetime", "line_items", "post_scripts"]
+        }
+    })
+}
+
+/// Build the JSON Schema for a single [`crate::Invoice`]
+///
+/// # Example
+/// ```rust
+/// let schema = invoice_pdf::schema::invoice_schema();
+/// assert_eq!(schema["$ref"], "#/$defs/invoice");
+/// ```
+pub fn invoice_schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": SCHEMA_ID,
+        "title": "Invoice",
+        "$ref": "#/$defs/invoice",
+        "$defs": definitions()
+    })
+}
+
+/// Build the JSON Schema for the array of invoices the CLI reads as JSON input
+pub fn invoice_batch_schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "$id": SCHEMA_ID.replace("invoice.schema.json", "invoice-batch.schema.json"),
+        "title": "Invoices",
+        "type": "array",
+        "items": { "$ref": "#/$defs/invoice" },
+        "$defs": definitions()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use gtin::Gtin;
+
+    use super::*;
+    use crate::{
+        AddressBuilder, Invoice, InvoiceBuilder, LineItemBuilder, PartyBuilder, PaymentBuilder,
+        decimal::{Precision, RoundingMode, RoundingPolicy},
+        defaults::{InvoiceDefaults, SenderProfile},
+        terms::PaymentTerms,
+    };
+
+    /// Properties that are resolved by [`InvoiceDefaults`] and never serialized
+    const INPUT_ONLY: [&str; 1] = ["sender_profile"];
+
+    /// Defaults with a sender, a sender profile `east` and a customer `acct`, so every form of
+    /// input the schema allows can be read
+    fn input_defaults() -> InvoiceDefaults {
+        let party = |name: &str| PartyBuilder::default().name(name).build().unwrap();
+        let mut defaults = InvoiceDefaults {
+            sender: Some(party("Default Sender")),
+            ..InvoiceDefaults::default()
+        };
+        defaults.profiles.insert(
+            String::from("east"),
+            SenderProfile {
+                sender: party("East LLC"),
+                logo: None,
+                payment_url_base: None,
+                post_scripts: Vec::new(),
+            },
+        );
+        defaults
+            .customers
+            .insert("acct", party("Customer"))
+            .unwrap();
+        defaults
+    }
+
+    /// An invoice with every optional field filled in, in its serialized form
+    fn full_invoice() -> Value {
+        let address = AddressBuilder::default()
+            .line1("1 Street St")
+            .line2("Apt 2")
+            .city("City")
+            .province_code("PR")
+            .postal_code("12345")
+            .build()
+            .unwrap();
+        let party = PartyBuilder::default()
+            .name("Party")
+            .phone("555-0100")
+            .email("party@example.com")
+            .address(address)
+            .build()
+            .unwrap();
+        let invoice = InvoiceBuilder::default()
+            .id("1")
+            .sender(party.clone())
+            .bill_to(party.clone())
+            .ship_to(party)
+            .logo("./logo.png")
+            .add_line(
+                LineItemBuilder::default()
+                    .sku("A")
+                    .title("Thing")
+                    .quantity(1)
+                    .price(1)
+                    .gtin(Gtin::new("082657543338").unwrap())
+                    .add_remark("remark")
+                    .build()
+                    .unwrap(),
+            )
+            .paid(1)
+            .add_payment(
+                PaymentBuilder::default()
+                    .amount(2)
+                    .method("check")
+                    .reference("1042")
+                    .build()
+                    .unwrap(),
+            )
+            .acct_id("acct")
+            .purchase_order("po")
+            .add_post_script("thanks")
+            .payment_url("https://example.com/pay")
+            .terms("2/10 Net 30".parse::<PaymentTerms>().unwrap())
+            .net_due_datetime(chrono::DateTime::parse_from_rfc3339("2026-03-12T12:00:00Z").unwrap())
+            .overdue_as_of(chrono::DateTime::parse_from_rfc3339("2026-04-01T12:00:00Z").unwrap())
+            .rounding(RoundingPolicy {
+                price: Precision::new(4, RoundingMode::HalfUp),
+                ..RoundingPolicy::default()
+            })
+            .build()
+            .unwrap();
+        serde_json::to_value(invoice).unwrap()
+    }
+
+    /// Where an example of each schema definition lives in [`full_invoice`]
+    const DEFINITION_POINTERS: [(&str, &str); 7] = [
+        ("invoice", ""),
+        ("party", "/sender"),
+        ("address", "/sender/address"),
+        ("lineItem", "/line_items/0"),
+        ("payment", "/payments/0"),
+        ("rounding", "/rounding"),
+        ("precision", "/rounding/price"),
+    ];
+
+    #[test]
+    fn test_schema_properties_match_serde_model() {
+        let schema = invoice_schema();
+        let full = full_invoice();
+        for (definition, pointer) in DEFINITION_POINTERS {
+            let expected: BTreeSet<_> = schema["$defs"][definition]["properties"]
+                .as_object()
+                .unwrap()
+                .keys()
+                .filter(|key| !INPUT_ONLY.contains(&key.as_str()))
+                .cloned()
+                .collect();
+            let actual: BTreeSet<_> = full
+                .pointer(pointer)
+                .unwrap()
+                .as_object()
+                .unwrap()
+                .keys()
+                .cloned()
+                .collect();
+            assert_eq!(expected, actual, "properties of {definition}");
+        }
+    }
+
+    #[test]
+    fn test_schema_required_matches_serde_model() {
+        let schema = invoice_schema();
+        let defaults = input_defaults();
+        for (definition, pointer) in DEFINITION_POINTERS {
+            let required: Vec<_> = schema["$defs"][definition]["required"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|r| r.as_str().unwrap())
+                .collect();
+            for property in schema["$defs"][definition]["properties"]
+                .as_object()
+                .unwrap()
+                .keys()
+            {
+                let mut full = full_invoice();
+                full.pointer_mut(pointer)
+                    .unwrap()
+                    .as_object_mut()
+                    .unwrap()
+                    .remove(property);
+                let parsed = defaults.invoice_from_value(full);
+                assert_eq!(
+                    parsed.is_err(),
+                    required.contains(&property.as_str()),
+                    "{definition}.{property} required-ness differs from serde"
+                );
+            }
+        }
+    }
+
+    /// Whether one of the alternatives of a property of the schema is a plain string
+    fn allows_string(property: &Value) -> bool {
+        ["oneOf", "anyOf"].iter().any(|key| {
+            property[key]
+                .as_array()
+                .is_some_and(|options| options.contains(&json!({"type": "string"})))
+        })
+    }
+
+    #[test]
+    fn test_customer_references_and_sender_profiles() {
+        let schema = invoice_schema();
+        let properties = &schema["$defs"]["invoice"]["properties"];
+        let defaults = input_defaults();
+
+        for field in ["bill_to", "ship_to"] {
+            assert!(allows_string(&properties[field]), "schema on {field}");
+            let mut full = full_invoice();
+            full[field] = json!("acct");
+            let invoice = defaults.invoice_from_value(full).unwrap();
+            let party = match field {
+                "bill_to" => invoice.bill_to(),
+                _ => invoice.ship_to().as_ref().unwrap(),
+            };
+            assert_eq!(party.name(), "Customer", "serde on {field}");
+        }
+
+        assert!(properties["sender_profile"].is_object());
+        let mut full = full_invoice();
+        let fields = full.as_object_mut().unwrap();
+        fields.remove("bill_to");
+        fields.remove("sender");
+        fields.insert(String::from("sender_profile"), json!("east"));
+        let invoice = defaults.invoice_from_value(full).unwrap();
+        assert_eq!(invoice.bill_to().name(), "Customer");
+        assert_eq!(invoice.sender().name(), "East LLC");
+    }
+
+    /// Whether the published schema's `decimal` definition accepts `value`
+    fn published_decimal_accepts(value: &Value) -> bool {
+        let published: Value =
+            serde_json::from_str(include_str!("../schema/invoice.schema.json")).unwrap();
+        published["$defs"]["decimal"]["anyOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|option| match (option["type"].as_str().unwrap(), value) {
+                ("string", Value::String(s)) => {
+                    regex::Regex::new(option["pattern"].as_str().unwrap())
+                        .unwrap()
+                        .is_match(s)
+                }
+                ("number", Value::Number(_)) => true,
+                _ => false,
+            })
+    }
+
+    #[test]
+    fn test_decimals_accept_strings_and_numbers() {
+        for (sample, accepted) in [
+            (json!("12.50"), true),
+            (json!("-1.25e1"), true),
+            (json!("+3"), true),
+            (json!(".5"), true),
+            (json!("7."), true),
+            (json!(2), true),
+            (json!(1.5), true),
+            (json!(""), false),
+            (json!("1,000"), false),
+            (json!("1.2.3"), false),
+            (json!("twelve"), false),
+            (json!("1e"), false),
+            (json!(true), false),
+            (json!(null), false),
+        ] {
+            assert_eq!(
+                published_decimal_accepts(&sample),
+                accepted,
+                "schema on {sample}"
+            );
+            let mut full = full_invoice();
+            full["line_items"][0]["price"] = sample.clone();
+            assert_eq!(
+                serde_json::from_value::<Invoice>(full).is_ok(),
+                accepted,
+                "serde on {sample}"
+            );
+        }
+
+        let mut full = full_invoice();
+        full["paid"] = json!(1.5);
+        full["line_items"][0]["quantity"] = json!(2);
+        full["line_items"][0]["price"] = json!("-1.25e1");
+        let invoice = serde_json::from_value::<Invoice>(full).unwrap();
+        assert_eq!(&invoice.total().to_string(), "-25.00");
+    }
+
+    #[test]
+    fn test_published_schema_is_current() {
+        let published: Value =
+            serde_json::from_str(include_str!("../schema/invoice.schema.json")).unwrap();
+        assert_eq!(
+            published,
+            invoice_schema(),
+            "run `invoice-pdf-cli schema > invoice-pdf/schema/invoice.schema.json`"
+        );
+    }
+}
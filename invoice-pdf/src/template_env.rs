@@ -9,7 +9,7 @@ use std::io::Cursor;
 
 use askama::Template;
 use base64::{Engine, engine::general_purpose};
-use bigdecimal::BigDecimal;
+use bigdecimal::{BigDecimal, Zero};
 use chrono::{DateTime, FixedOffset};
 use image::Luma;
 use qrcode::QrCode;
@@ -18,7 +18,7 @@ use crate::invoice::Invoice;
 
 /// Define the filters module for Askama.
 /// Askama automatically looks for a `filters` module in the same scope as the template.
-mod filters {
+pub(crate) mod filters {
     use super::*;
 
     pub fn format_ymd_helper(dt: &DateTime<FixedOffset>) -> String {
@@ -56,10 +56,50 @@ pub struct InvoiceTemplate<'a> {
 }
 
 impl<'a> InvoiceTemplate<'a> {
+    /// The decimal places unit prices are printed with
+    pub fn price_places(&self) -> usize {
+        self.invoice.rounding().price.places as usize
+    }
+
+    /// The decimal places line totals and amounts are printed with
+    pub fn total_places(&self) -> usize {
+        self.invoice.rounding().total.places as usize
+    }
+
+    /// The early-payment discount and the date it must be paid by, such as
+    /// `$2.47 if paid by 2026-01-30`, if the terms offer one
+    pub fn discount_text(&self) -> Option<String> {
+        let amount = self.invoice.discount_amount()?;
+        let deadline = self.invoice.discount_deadline()?;
+        Some(format!(
+            "{} if paid by {}",
+            filters::pretty_price_helper(amount, self.total_places()),
+            filters::format_ymd_helper(&deadline)
+        ))
+    }
+
+    /// Whether part of the amount paid is not itemized in the payment history
+    pub fn has_unitemized_paid(&self) -> bool {
+        !self.invoice.unitemized_paid().is_zero()
+    }
+
     /// Returns the logo as a base64 encoded data URI if it exists.
+    ///
+    /// A logo that cannot be read is left out. Use [`Self::try_logo_data_uri`] to find out why.
     pub fn logo_data_uri(&self) -> Option<String> {
-        let path = self.invoice.logo().as_ref()?;
-        let data = std::fs::read(path).ok()?;
+        self.try_logo_data_uri().ok().flatten()
+    }
+
+    /// Returns the logo as a base64 encoded data URI, or `None` if the invoice has no logo
+    ///
+    /// # Errors
+    /// - [`crate::Error`] of kind [`crate::error::ErrorKind::InvalidLogo`] if the logo file cannot
+    ///   be read
+    pub fn try_logo_data_uri(&self) -> Result<Option<String>, crate::Error> {
+        let Some(path) = self.invoice.logo().as_ref() else {
+            return Ok(None);
+        };
+        let data = std::fs::read(path).map_err(|e| crate::Error::invalid_logo(path, e))?;
         let encoded = general_purpose::STANDARD.encode(&data);
         let extension = path
             .extension()
@@ -79,7 +119,7 @@ impl<'a> InvoiceTemplate<'a> {
             _ => "image/png",
         };
 
-        Some(format!("data:{};base64,{}", mime_type, encoded))
+        Ok(Some(format!("data:{};base64,{}", mime_type, encoded)))
     }
 
     /// Creates a data url containing a base64 encoded qrcode image that will take the user to a
@@ -88,15 +128,28 @@ impl<'a> InvoiceTemplate<'a> {
     /// If there is no payment portal or if the qrcode image or base64 encoding fails, then return
     /// `None`
     pub fn payment_qrcode_data_uri(&self) -> Option<String> {
-        let payment_url = self.invoice.payment_url().as_ref()?.as_bytes();
-        let qrcode = QrCode::new(payment_url).ok()?;
+        self.try_payment_qrcode_data_uri().ok().flatten()
+    }
+
+    /// Creates the payment qrcode data url, or `None` if the invoice has no payment portal
+    ///
+    /// # Errors
+    /// - [`crate::Error`] of kind [`crate::error::ErrorKind::QrCode`] if the payment url does not
+    ///   fit in a qrcode or the image cannot be encoded
+    pub fn try_payment_qrcode_data_uri(&self) -> Result<Option<String>, crate::Error> {
+        let Some(payment_url) = self.invoice.payment_url().as_ref() else {
+            return Ok(None);
+        };
+        let qrcode = QrCode::new(payment_url.as_bytes()).map_err(crate::Error::qr_code)?;
         let image = qrcode.render::<Luma<u8>>().max_dimensions(100, 100).build();
         let mut buf = Cursor::new(Vec::new());
         let dynimg = image::DynamicImage::ImageLuma8(image);
-        dynimg.write_to(&mut buf, image::ImageFormat::Png).ok()?;
+        dynimg
+            .write_to(&mut buf, image::ImageFormat::Png)
+            .map_err(crate::Error::qr_code)?;
         let image_bytes = buf.into_inner();
         let encoded = general_purpose::STANDARD.encode(&image_bytes);
-        Some(format!("data:image/png;base64,{}", encoded))
+        Ok(Some(format!("data:image/png;base64,{}", encoded)))
     }
 }
 
@@ -105,14 +158,14 @@ mod tests {
     use chrono::TimeZone;
     use std::str::FromStr;
 
-    use crate::{InvoiceBuilder, LineItemBuilder, PartyBuilder};
+    use crate::{InvoiceBuilder, LineItemBuilder, PartyBuilder, PaymentBuilder};
 
     use super::*;
 
     #[test]
     fn test_format_ymd() {
         let dt = chrono::Utc
-            .with_ymd_and_hms(2026, 02, 09, 12, 00, 00)
+            .with_ymd_and_hms(2026, 2, 9, 12, 0, 0)
             .unwrap()
             .into();
         assert_eq!(filters::format_ymd_helper(&dt), "2026-02-09");
@@ -136,6 +189,7 @@ mod tests {
                     .quantity(2)
                     .price(BigDecimal::from(10))
                     .title("this is a test")
+                    .add_remark("a remark")
                     .build()
                     .unwrap(),
             )
@@ -158,8 +212,77 @@ mod tests {
         assert!(render.contains("bill_to"));
         assert!(render.contains("<td>test</td>"));
         assert!(render.contains("<td>this is a test</td>"));
+        assert!(render.contains(r#"<br><span class="remark">a remark</span></td>"#));
         assert!(render.contains(r#"<td style="text-align: right;">$20.00</td>"#));
         assert!(render.contains(r#"<td style="text-align:right;">$30.00</td>"#));
         assert!(render.contains(r#"<td style="text-align:right;">$29.00</td>"#));
+        assert!(!render.contains("Payment History"));
+        assert!(!render.contains("Terms:"));
+    }
+
+    #[test]
+    fn test_render_terms() {
+        let created = chrono::Utc.with_ymd_and_hms(2026, 1, 20, 12, 0, 0).unwrap();
+        let inv = InvoiceBuilder::default()
+            .id("test id")
+            .created_datetime(created)
+            .terms("2/10 Net 30".parse::<crate::terms::PaymentTerms>().unwrap())
+            .sender(PartyBuilder::default().name("sender").build().unwrap())
+            .bill_to(PartyBuilder::default().name("bill_to").build().unwrap())
+            .add_line(
+                LineItemBuilder::default()
+                    .sku("test")
+                    .quantity(1)
+                    .price(BigDecimal::from_str("123.45").unwrap())
+                    .title("this is a test")
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+        let render = InvoiceTemplate { invoice: &inv }.render().unwrap();
+        assert!(render.contains("<td>2026-02-19</td>"));
+        assert!(render.contains("<td>2/10 Net 30</td>"));
+        assert!(render.contains("<td>$2.47 if paid by 2026-01-30</td>"));
+    }
+
+    #[test]
+    fn test_render_payment_history() {
+        let received = chrono::Utc.with_ymd_and_hms(2026, 3, 2, 12, 0, 0).unwrap();
+        let inv = InvoiceBuilder::default()
+            .id("test id")
+            .sender(PartyBuilder::default().name("sender").build().unwrap())
+            .bill_to(PartyBuilder::default().name("bill_to").build().unwrap())
+            .add_line(
+                LineItemBuilder::default()
+                    .sku("test")
+                    .quantity(3)
+                    .price(BigDecimal::from(10))
+                    .title("this is a test")
+                    .build()
+                    .unwrap(),
+            )
+            .paid(BigDecimal::from(1))
+            .add_payment(
+                PaymentBuilder::default()
+                    .received_datetime(received)
+                    .amount(BigDecimal::from_str("12.5").unwrap())
+                    .method("check")
+                    .reference("1042")
+                    .build()
+                    .unwrap(),
+            )
+            .build()
+            .unwrap();
+        let render = InvoiceTemplate { invoice: &inv }.render().unwrap();
+        assert!(render.contains("<caption>Payment History</caption>"));
+        assert!(render.contains("<td>Previously paid</td>"));
+        assert!(render.contains(r#"<td style="text-align: right;">$1.00</td>"#));
+        assert!(render.contains("<td>2026-03-02</td>"));
+        assert!(render.contains("<td>check</td>"));
+        assert!(render.contains("<td>1042</td>"));
+        assert!(render.contains(r#"<td style="text-align: right;">$12.50</td>"#));
+        assert!(render.contains(r#"<td style="text-align:right;">$13.50</td>"#));
+        assert!(render.contains(r#"<td style="text-align:right;">$16.50</td>"#));
     }
 }
@@ -0,0 +1,377 @@
+//! Payment terms and early-payment discounts.
+//!
+//! [`PaymentTerms`] are written the way they are printed on an invoice, such as `Net 30`,
+//! `Due on receipt`, `EOM`, `Net 10 EOM` or `2/10 Net 30`. An invoice with terms and no
+//! `net_due_datetime` of its own is due on the date its terms give, counted from its
+//! `created_datetime`. Terms starting with a discount, such as the `2/10` of `2/10 Net 30`, take
+//! that percentage off the invoice total when it is paid within that many days. The percentage
+//! must be between 0 and 100, and the discount may not outlast the days the terms give to pay.
+//!
+//! ```rust
+//! use invoice_pdf::terms::PaymentTerms;
+//!
+//! let terms: PaymentTerms = "2/10 net 30".parse().unwrap();
+//! assert_eq!(terms.to_string(), "2/10 Net 30");
+//! let created = chrono::DateTime::parse_from_rfc3339("2026-02-10T12:00:00-05:00").unwrap();
+//! assert_eq!(
+//!     terms.due_datetime(&created).unwrap().to_rfc3339(),
+//!     "2026-03-12T12:00:00-05:00"
+//! );
+//! ```
+
+use std::{fmt::Display, str::FromStr};
+
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Datelike, Days, FixedOffset, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+
+/// When an invoice is due
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DueTerms {
+    /// Due the moment it is created
+    OnReceipt,
+    /// Due the given number of days after it is created
+    Net(u32),
+    /// Due the given number of days after the end of the month it is created in
+    EndOfMonth(u32),
+}
+
+/// A percentage taken off the total for paying early
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EarlyPaymentDiscount {
+    /// The percentage of the total taken off, such as 2 for 2%
+    pub percent: BigDecimal,
+    /// How many days after the invoice is created the discount may be taken
+    pub days: u32,
+}
+
+/// The payment terms of an invoice, such as `2/10 Net 30`
+///
+/// Serializes as its printed text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PaymentTerms {
+    /// When the invoice is due
+    pub due: DueTerms,
+    /// The discount for paying early, if any
+    pub discount: Option<EarlyPaymentDiscount>,
+}
+
+/// The error for a date the terms would move past the last date that can be represented
+fn out_of_range(datetime: &DateTime<FixedOffset>, days: u32) -> crate::Error {
+    crate::Error::invalid(format!(
+        "{days} days after {} is past the last supported date",
+        datetime.to_rfc3339()
+    ))
+}
+
+/// Move `datetime` forward by `days`, keeping its time of day and offset
+///
+/// # Errors
+/// - [`crate::Error`] if the result is past the last date that can be represented
+fn add_days(
+    datetime: &DateTime<FixedOffset>,
+    days: u32,
+) -> Result<DateTime<FixedOffset>, crate::Error> {
+    datetime
+        .checked_add_days(Days::new(u64::from(days)))
+        .ok_or_else(|| out_of_range(datetime, days))
+}
+
+impl PaymentTerms {
+    /// Terms with no discount
+    pub fn new(due: DueTerms) -> Self {
+        Self {
+            due,
+            discount: None,
+        }
+    }
+
+    /// Add an early-payment discount of `percent` for paying within `days`
+    ///
+    /// # Errors
+    /// - [`crate::Error`] if the discount is not allowed, see [`Self::check`]
+    pub fn with_discount(
+        self,
+        percent: impl Into<BigDecimal>,
+        days: u32,
+    ) -> Result<Self, crate::Error> {
+        let terms = Self {
+            discount: Some(EarlyPaymentDiscount {
+                percent: percent.into(),
+                days,
+            }),
+            ..self
+        };
+        terms.check()?;
+        Ok(terms)
+    }
+
+    /// Check that the discount, if any, is between 0 and 100 percent and ends no later than the
+    /// invoice can be due. Terms due at the end of the month may be due on the day they are
+    /// created plus their days, so their discount may not run longer than that
+    ///
+    /// # Errors
+    /// - [`crate::Error`] describing the first problem with the discount
+    pub fn check(&self) -> Result<(), crate::Error> {
+        let Some(discount) = &self.discount else {
+            return Ok(());
+        };
+        if !(BigDecimal::from(0)..=BigDecimal::from(100)).contains(&discount.percent) {
+            return Err(crate::Error::invalid(format!(
+                "discount of {}% in payment terms '{self}' is not between 0 and 100",
+                discount.percent.normalized()
+            )));
+        }
+        let net_days = match self.due {
+            DueTerms::OnReceipt => 0,
+            DueTerms::Net(days) | DueTerms::EndOfMonth(days) => days,
+        };
+        if discount.days > net_days {
+            return Err(crate::Error::invalid(format!(
+                "discount period of {} days in payment terms '{self}' is longer than the {net_days} \
+                 days to pay",
+                discount.days
+            )));
+        }
+        Ok(())
+    }
+
+    /// The date an invoice created at `created` is due, at the same time of day
+    ///
+    /// # Errors
+    /// - [`crate::Error`] if the due date is past the last date that can be represented
+    pub fn due_datetime(
+        &self,
+        created: &DateTime<FixedOffset>,
+    ) -> Result<DateTime<FixedOffset>, crate::Error> {
+        match self.due {
+            DueTerms::OnReceipt => Ok(*created),
+            DueTerms::Net(days) => add_days(created, days),
+            DueTerms::EndOfMonth(days) => {
+                let end_of_month = NaiveDate::from_ymd_opt(created.year(), created.month(), 1)
+                    .and_then(|first| first.checked_add_months(Months::new(1)))
+                    .and_then(|first| first.pred_opt())
+                    .and_then(|last| {
+                        last.and_time(created.time())
+                            .and_local_timezone(*created.offset())
+                            .single()
+                    })
+                    .ok_or_else(|| out_of_range(created, days))?;
+                add_days(&end_of_month, days)
+            }
+        }
+    }
+
+    /// The last moment the early-payment discount may be taken for an invoice created at
+    /// `created`, if the terms have one
+    ///
+    /// # Errors
+    /// - [`crate::Error`] if the deadline is past the last date that can be represented
+    pub fn discount_deadline(
+        &self,
+        created: &DateTime<FixedOffset>,
+    ) -> Result<Option<DateTime<FixedOffset>>, crate::Error> {
+        self.discount
+            .as_ref()
+            .map(|discount| add_days(created, discount.days))
+            .transpose()
+    }
+
+    /// The unrounded early-payment discount on `total`, if the terms have one
+    pub fn discount_amount(&self, total: &BigDecimal) -> Option<BigDecimal> {
+        self.discount
+            .as_ref()
+            .map(|discount| total * &discount.percent / BigDecimal::from(100))
+    }
+}
+
+impl Display for PaymentTerms {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(discount) = &self.discount {
+            write!(f, "{}/{} ", discount.percent.normalized(), discount.days)?;
+        }
+        match self.due {
+            DueTerms::OnReceipt => write!(f, "Due on receipt"),
+            DueTerms::Net(days) => write!(f, "Net {days}"),
+            DueTerms::EndOfMonth(0) => write!(f, "EOM"),
+            DueTerms::EndOfMonth(days) => write!(f, "Net {days} EOM"),
+        }
+    }
+}
+
+impl FromStr for PaymentTerms {
+    type Err = crate::Error;
+
+    /// Parse terms such as `Net 30`, `Due on receipt`, `EOM`, `Net 10 EOM` or `2/10 Net 30`,
+    /// ignoring case and extra whitespace. The discount is checked with [`Self::check`]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            crate::Error::invalid(format!(
+                "unknown payment terms '{s}', expected terms such as 'Net 30', 'Due on receipt', \
+                 'EOM', 'Net 10 EOM' or '2/10 Net 30'"
+            ))
+        };
+        let lower = s.to_lowercase();
+        let mut words: Vec<&str> = lower.split_whitespace().collect();
+
+        let discount = match words.first().and_then(|word| word.split_once('/')) {
+            Some((percent, days)) => {
+                let percent = BigDecimal::from_str(percent).map_err(|_| invalid())?;
+                let days = days.parse().map_err(|_| invalid())?;
+                words.remove(0);
+                Some(EarlyPaymentDiscount { percent, days })
+            }
+            None => None,
+        };
+
+        let net_days = |days: &str| days.parse::<u32>().map_err(|_| invalid());
+        let due = match words.as_slice() {
+            ["due", "on", "receipt"] => DueTerms::OnReceipt,
+            ["net", days] => DueTerms::Net(net_days(days)?),
+            ["eom"] => DueTerms::EndOfMonth(0),
+            ["net", days, "eom"] => DueTerms::EndOfMonth(net_days(days)?),
+            _ => return Err(invalid()),
+        };
+        let terms = Self { due, discount };
+        terms.check()?;
+        Ok(terms)
+    }
+}
+
+impl TryFrom<String> for PaymentTerms {
+    type Error = crate::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<PaymentTerms> for String {
+    fn from(value: PaymentTerms) -> Self {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn created() -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339("2026-01-20T09:00:00-05:00").unwrap()
+    }
+
+    fn due(terms: &str) -> String {
+        let terms: PaymentTerms = terms.parse().unwrap();
+        terms.due_datetime(&created()).unwrap().to_rfc3339()
+    }
+
+    #[test]
+    fn test_parse_and_display() {
+        for (text, printed) in [
+            ("Net 30", "Net 30"),
+            ("  net   15 ", "Net 15"),
+            ("Due on receipt", "Due on receipt"),
+            ("eom", "EOM"),
+            ("Net 10 EOM", "Net 10 EOM"),
+            ("2/10 Net 30", "2/10 Net 30"),
+            ("1.50/10 net 30", "1.5/10 Net 30"),
+        ] {
+            let terms: PaymentTerms = text.parse().unwrap();
+            assert_eq!(terms.to_string(), printed);
+        }
+        for bad in [
+            "",
+            "Net",
+            "Net -5",
+            "Net 30 days",
+            "x/10 Net 30",
+            "-2/10 Net 30",
+            "101/10 Net 30",
+            "2/40 Net 30",
+            "2/1 Due on receipt",
+            "2/10",
+            "Cash",
+        ] {
+            let e = bad.parse::<PaymentTerms>().unwrap_err();
+            assert_eq!(e.kind.name(), "invalid", "{bad}");
+        }
+
+        let terms: PaymentTerms = serde_json::from_value(serde_json::json!("2/10 Net 30")).unwrap();
+        assert_eq!(
+            terms,
+            PaymentTerms::new(DueTerms::Net(30))
+                .with_discount(2, 10)
+                .unwrap()
+        );
+        assert_eq!(
+            PaymentTerms::new(DueTerms::Net(30))
+                .with_discount(150, 10)
+                .unwrap_err()
+                .to_string(),
+            "discount of 150% in payment terms '150/10 Net 30' is not between 0 and 100"
+        );
+        assert_eq!(
+            PaymentTerms::new(DueTerms::Net(30))
+                .with_discount(2, 40)
+                .unwrap_err()
+                .to_string(),
+            "discount period of 40 days in payment terms '2/40 Net 30' is longer than the 30 days \
+             to pay"
+        );
+        assert!(
+            PaymentTerms::new(DueTerms::EndOfMonth(10))
+                .with_discount(2, 10)
+                .is_ok()
+        );
+        assert_eq!(serde_json::to_value(&terms).unwrap(), "2/10 Net 30");
+        assert!(serde_json::from_value::<PaymentTerms>(serde_json::json!("Net")).is_err());
+    }
+
+    #[test]
+    fn test_due_datetime() {
+        assert_eq!(due("Due on receipt"), "2026-01-20T09:00:00-05:00");
+        assert_eq!(due("Net 30"), "2026-02-19T09:00:00-05:00");
+        assert_eq!(due("EOM"), "2026-01-31T09:00:00-05:00");
+        assert_eq!(due("Net 10 EOM"), "2026-02-10T09:00:00-05:00");
+
+        let february = DateTime::parse_from_rfc3339("2028-02-05T00:00:00Z").unwrap();
+        let eom = PaymentTerms::new(DueTerms::EndOfMonth(0));
+        assert_eq!(
+            eom.due_datetime(&february).unwrap().to_rfc3339(),
+            "2028-02-29T00:00:00+00:00"
+        );
+
+        let last = DateTime::<chrono::Utc>::MAX_UTC.fixed_offset();
+        let net: PaymentTerms = "2/10 Net 30".parse().unwrap();
+        assert_eq!(net.due_datetime(&last).unwrap_err().kind.name(), "invalid");
+        assert!(net.discount_deadline(&last).is_err());
+        assert!(eom.due_datetime(&last).is_err());
+    }
+
+    #[test]
+    fn test_discount() {
+        let terms: PaymentTerms = "2/10 Net 30".parse().unwrap();
+        assert_eq!(
+            terms
+                .discount_deadline(&created())
+                .unwrap()
+                .unwrap()
+                .to_rfc3339(),
+            "2026-01-30T09:00:00-05:00"
+        );
+        let total = BigDecimal::from_str("123.45").unwrap();
+        assert_eq!(
+            terms
+                .discount_amount(&total)
+                .unwrap()
+                .normalized()
+                .to_string(),
+            "2.469"
+        );
+
+        let net: PaymentTerms = "Net 30".parse().unwrap();
+        assert_eq!(net.discount_deadline(&created()).unwrap(), None);
+        assert_eq!(net.discount_amount(&total), None);
+    }
+}
@@ -0,0 +1,463 @@
+//! Business rules checked before an invoice is rendered.
+//!
+//! An [`Invoice`] can be deserialized or built with values that make no sense on paper, such as
+//! no line items, a negative quantity or a due date before the invoice was created.
+//! [`Invoice::validate`] checks every [`Rule`] and reports what it finds as [`Issue`]s, each
+//! with the path of the offending field. Whether a rule is an error, only a warning or not
+//! checked at all is set with [`ValidationRules`].
+//!
+//! ```rust
+//! use invoice_pdf::{
+//!     InvoiceBuilder, PartyBuilder,
+//!     validation::{Rule, Severity, ValidationRules},
+//! };
+//!
+//! let invoice = InvoiceBuilder::default()
+//!     .id("1")
+//!     .sender(PartyBuilder::default().name("S").build().unwrap())
+//!     .bill_to(PartyBuilder::default().name("B").build().unwrap())
+//!     .build()
+//!     .unwrap();
+//! let validation = invoice.validate();
+//! assert!(!validation.is_valid());
+//! assert_eq!(validation.errors().next().unwrap().path, "line_items");
+//!
+//! let rules = ValidationRules::default().with(Rule::NoLineItems, Severity::Warning);
+//! assert!(invoice.validate_with(&rules).is_valid());
+//! ```
+
+use std::{collections::BTreeMap, fmt::Display};
+
+use bigdecimal::{BigDecimal, Zero};
+use serde::{Deserialize, Serialize};
+
+use crate::{Invoice, error::ErrorKind};
+
+/// How much a broken [`Rule`] matters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The invoice is not rendered
+    Error,
+    /// The invoice is rendered, but the issue is reported
+    Warning,
+    /// The rule is not checked
+    Off,
+}
+
+/// A business rule an invoice is checked against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rule {
+    /// The invoice id is blank
+    EmptyId,
+    /// The sender has a blank name
+    EmptySenderName,
+    /// The billed party has a blank name
+    EmptyBillToName,
+    /// The invoice has no line items
+    NoLineItems,
+    /// A line item has a quantity below zero
+    NegativeQuantity,
+    /// A line item has a quantity of zero
+    ZeroQuantity,
+    /// A line item has a unit price below zero, as used for credits
+    NegativePrice,
+    /// The due date is before the date the invoice was created
+    DueBeforeCreated,
+    /// The amount paid is below zero
+    NegativePaid,
+    /// A payment has an amount below zero, as used for refunds
+    NegativePayment,
+    /// More has been paid than the invoice total
+    Overpaid,
+}
+
+impl Rule {
+    /// Every rule, in the order they are checked
+    pub const ALL: [Rule; 11] = [
+        Rule::EmptyId,
+        Rule::EmptySenderName,
+        Rule::EmptyBillToName,
+        Rule::NoLineItems,
+        Rule::NegativeQuantity,
+        Rule::ZeroQuantity,
+        Rule::NegativePrice,
+        Rule::DueBeforeCreated,
+        Rule::NegativePaid,
+        Rule::NegativePayment,
+        Rule::Overpaid,
+    ];
+
+    /// The severity used when [`ValidationRules`] does not set one
+    pub fn default_severity(self) -> Severity {
+        match self {
+            Rule::ZeroQuantity | Rule::NegativePrice | Rule::NegativePayment => Severity::Warning,
+            _ => Severity::Error,
+        }
+    }
+
+    /// The name of the rule as written in config files
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::EmptyId => "empty_id",
+            Rule::EmptySenderName => "empty_sender_name",
+            Rule::EmptyBillToName => "empty_bill_to_name",
+            Rule::NoLineItems => "no_line_items",
+            Rule::NegativeQuantity => "negative_quantity",
+            Rule::ZeroQuantity => "zero_quantity",
+            Rule::NegativePrice => "negative_price",
+            Rule::DueBeforeCreated => "due_before_created",
+            Rule::NegativePaid => "negative_paid",
+            Rule::NegativePayment => "negative_payment",
+            Rule::Overpaid => "overpaid",
+        }
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The severity of each [`Rule`], where rules not set keep their
+/// [`Rule::default_severity`]
+///
+/// Deserializes from a map of rule names to severities, such as the `[validation]` table of a
+/// config file:
+///
+/// ```toml
+/// negative_price = "off"
+/// overpaid = "warning"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ValidationRules {
+    severities: BTreeMap<Rule, Severity>,
+}
+
+impl ValidationRules {
+    /// Check `rule` with `severity` instead of its default
+    pub fn with(mut self, rule: Rule, severity: Severity) -> Self {
+        self.severities.insert(rule, severity);
+        self
+    }
+
+    /// The severity `rule` is checked with
+    pub fn severity(&self, rule: Rule) -> Severity {
+        self.severities
+            .get(&rule)
+            .copied()
+            .unwrap_or_else(|| rule.default_severity())
+    }
+}
+
+/// A broken [`Rule`] found by [`Invoice::validate`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Issue {
+    pub rule: Rule,
+    pub severity: Severity,
+    /// Path of the field the issue is about, such as `line_items[3].quantity`
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// The outcome of validating an invoice
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Validation {
+    pub issues: Vec<Issue>,
+}
+
+impl Validation {
+    /// The issues that stop the invoice from being rendered
+    pub fn errors(&self) -> impl Iterator<Item = &Issue> {
+        self.issues.iter().filter(|i| i.severity == Severity::Error)
+    }
+
+    /// The issues that are only reported
+    pub fn warnings(&self) -> impl Iterator<Item = &Issue> {
+        self.issues
+            .iter()
+            .filter(|i| i.severity == Severity::Warning)
+    }
+
+    /// Whether no issue is an error
+    pub fn is_valid(&self) -> bool {
+        self.errors().next().is_none()
+    }
+
+    /// The warnings if the invoice is valid
+    ///
+    /// # Errors
+    /// - [`crate::Error`] of kind [`ErrorKind::Validation`] holding every error issue
+    pub fn into_result(self) -> Result<Vec<Issue>, crate::Error> {
+        let (errors, warnings) = self
+            .issues
+            .into_iter()
+            .partition::<Vec<_>, _>(|i| i.severity == Severity::Error);
+        if errors.is_empty() {
+            Ok(warnings)
+        } else {
+            Err(crate::Error::new(ErrorKind::Validation(errors)))
+        }
+    }
+}
+
+/// Collects the issues of one invoice, skipping rules that are turned off
+struct Checker<'a> {
+    rules: &'a ValidationRules,
+    issues: Vec<Issue>,
+}
+
+impl Checker<'_> {
+    fn check(&mut self, rule: Rule, broken: bool, path: impl Into<String>, message: String) {
+        if !broken {
+            return;
+        }
+        let severity = self.rules.severity(rule);
+        if severity == Severity::Off {
+            return;
+        }
+        self.issues.push(Issue {
+            rule,
+            severity,
+            path: path.into(),
+            message,
+        });
+    }
+}
+
+impl Invoice {
+    /// Check the invoice against every [`Rule`] with its default severity
+    pub fn validate(&self) -> Validation {
+        self.validate_with(&ValidationRules::default())
+    }
+
+    /// Check the invoice against every [`Rule`] with the severities set in `rules`
+    pub fn validate_with(&self, rules: &ValidationRules) -> Validation {
+        let mut checker = Checker {
+            rules,
+            issues: Vec::new(),
+        };
+        checker.check(
+            Rule::EmptyId,
+            self.id().trim().is_empty(),
+            "id",
+            String::from("invoice id is empty"),
+        );
+        checker.check(
+            Rule::EmptySenderName,
+            self.sender().name().trim().is_empty(),
+            "sender.name",
+            String::from("sender name is empty"),
+        );
+        checker.check(
+            Rule::EmptyBillToName,
+            self.bill_to().name().trim().is_empty(),
+            "bill_to.name",
+            String::from("bill to name is empty"),
+        );
+        checker.check(
+            Rule::NoLineItems,
+            self.line_items().is_empty(),
+            "line_items",
+            String::from("invoice has no line items"),
+        );
+        for (i, line) in self.line_items().iter().enumerate() {
+            let quantity = line.quantity();
+            checker.check(
+                Rule::NegativeQuantity,
+                quantity < BigDecimal::zero(),
+                format!("line_items[{i}].quantity"),
+                format!("quantity {quantity} is negative"),
+            );
+            checker.check(
+                Rule::ZeroQuantity,
+                quantity.is_zero(),
+                format!("line_items[{i}].quantity"),
+                String::from("quantity is zero"),
+            );
+            let price = line.price();
+            checker.check(
+                Rule::NegativePrice,
+                price < BigDecimal::zero(),
+                format!("line_items[{i}].price"),
+                format!("price {price} is negative"),
+            );
+        }
+        checker.check(
+            Rule::DueBeforeCreated,
+            self.net_due_datetime() < self.created_datetime(),
+            "net_due_datetime",
+            format!(
+                "due date {} is before the created date {}",
+                self.net_due_datetime().to_rfc3339(),
+                self.created_datetime().to_rfc3339()
+            ),
+        );
+        let amount = |value: &BigDecimal| self.rounding().total.format(value);
+        let paid = self.paid();
+        checker.check(
+            Rule::NegativePaid,
+            paid < BigDecimal::zero(),
+            "paid",
+            format!("amount paid {} is negative", amount(&paid)),
+        );
+        for (i, payment) in self.payments().iter().enumerate() {
+            let payment = payment.amount();
+            checker.check(
+                Rule::NegativePayment,
+                payment < BigDecimal::zero(),
+                format!("payments[{i}].amount"),
+                format!("payment of {} is negative", amount(&payment)),
+            );
+        }
+        let total = self.total();
+        checker.check(
+            Rule::Overpaid,
+            paid > BigDecimal::zero() && paid > total,
+            "paid",
+            format!(
+                "amount paid {} is more than the total {}",
+                amount(&paid),
+                amount(&total)
+            ),
+        );
+        Validation {
+            issues: checker.issues,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InvoiceBuilder;
+    use crate::invoice::tests::invoice_from_json as invoice;
+
+    fn paths(validation: &Validation) -> Vec<(&str, Rule, Severity)> {
+        validation
+            .issues
+            .iter()
+            .map(|i| (i.path.as_str(), i.rule, i.severity))
+            .collect()
+    }
+
+    #[test]
+    fn test_valid_invoice() {
+        let validation = invoice(serde_json::json!({})).validate();
+        assert!(validation.issues.is_empty());
+        assert_eq!(validation.into_result().unwrap(), Vec::new());
+    }
+
+    #[test]
+    fn test_rules() {
+        let validation = invoice(serde_json::json!({
+            "net_due_datetime": "2024-12-01T00:00:00Z",
+            "sender": {"name": " "},
+            "line_items": [
+                {"sku": "A", "title": "T", "quantity": "2", "price": "5"},
+                {"sku": "B", "title": "T", "quantity": "-1", "price": "-3"},
+                {"sku": "C", "title": "T", "quantity": "0", "price": "1"},
+            ],
+            "paid": "40",
+            "payments": [
+                {"received_datetime": "2025-01-02T00:00:00Z", "amount": "15"},
+                {"received_datetime": "2025-01-03T00:00:00Z", "amount": "-5"},
+            ],
+        }))
+        .validate();
+        assert_eq!(
+            paths(&validation),
+            vec![
+                ("sender.name", Rule::EmptySenderName, Severity::Error),
+                (
+                    "line_items[1].quantity",
+                    Rule::NegativeQuantity,
+                    Severity::Error
+                ),
+                (
+                    "line_items[1].price",
+                    Rule::NegativePrice,
+                    Severity::Warning
+                ),
+                (
+                    "line_items[2].quantity",
+                    Rule::ZeroQuantity,
+                    Severity::Warning
+                ),
+                ("net_due_datetime", Rule::DueBeforeCreated, Severity::Error),
+                (
+                    "payments[1].amount",
+                    Rule::NegativePayment,
+                    Severity::Warning
+                ),
+                ("paid", Rule::Overpaid, Severity::Error),
+            ]
+        );
+        assert_eq!(
+            validation.issues[6].to_string(),
+            "paid: amount paid 50.00 is more than the total 13.00"
+        );
+
+        // Amounts are printed with the invoice's total places
+        let whole = invoice(serde_json::json!({
+            "paid": "12",
+            "rounding": {"total": {"places": 0, "mode": "half_even"}},
+        }))
+        .validate();
+        assert_eq!(
+            whole.issues[0].to_string(),
+            "paid: amount paid 12 is more than the total 10"
+        );
+
+        let e = validation.into_result().unwrap_err();
+        assert_eq!(e.kind.name(), "validation");
+        assert!(
+            e.to_string()
+                .starts_with("invoice failed 4 validation rule(s): sender.name: sender name is empty; line_items[1].quantity"),
+            "{e}"
+        );
+    }
+
+    #[test]
+    fn test_configured_severity() {
+        let invoice = InvoiceBuilder::default()
+            .id("")
+            .sender(crate::PartyBuilder::default().name("S").build().unwrap())
+            .bill_to(crate::PartyBuilder::default().name("").build().unwrap())
+            .paid(-1)
+            .build()
+            .unwrap();
+        let rules: ValidationRules = serde_json::from_value(serde_json::json!({
+            "empty_id": "warning",
+            "no_line_items": "off",
+            "negative_paid": "warning",
+        }))
+        .unwrap();
+        let validation = invoice.validate_with(&rules);
+        assert_eq!(
+            paths(&validation),
+            vec![
+                ("id", Rule::EmptyId, Severity::Warning),
+                ("bill_to.name", Rule::EmptyBillToName, Severity::Error),
+                ("paid", Rule::NegativePaid, Severity::Warning),
+            ]
+        );
+
+        let rules = rules.with(Rule::EmptyBillToName, Severity::Warning);
+        let warnings = invoice.validate_with(&rules).into_result().unwrap();
+        assert_eq!(warnings.len(), 3);
+
+        assert!(
+            serde_json::from_value::<ValidationRules>(serde_json::json!({"bogus": "off"})).is_err()
+        );
+    }
+}